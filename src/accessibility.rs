@@ -0,0 +1,280 @@
+//! Accessibility options, saved in [`Settings`] and applied as soon as they change.
+//!
+//! * C: cycle the palettes for colour-vision deficiencies, which recolour the aliens and tell the
+//!   players' shots apart from the aliens'
+//! * V: reduce flashing, which dims the fire of chain reactions
+//! * [ and ]: slow the game down to as little as half speed, or speed it back up
+//! * T: auto-fire, so every player fires without holding the button
+//! * Z: larger text
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::alien::{Alien, AlienType};
+use crate::alien_projectile::AlienProjectile;
+use crate::localization::Localized;
+use crate::projectile::Projectile;
+use crate::settings::Settings;
+use crate::widget::{self, BaseFontSize};
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                change_accessibility,
+                apply_game_speed.run_if(resource_changed::<Settings>),
+                recolor_sprites,
+                scale_text,
+                destroy_accessibility_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+pub const MIN_GAME_SPEED: f32 = 0.5;
+const GAME_SPEED_STEP: f32 = 0.1;
+const LARGE_TEXT_SCALE: f32 = 1.5;
+//how bright fire is drawn with reduced flashing
+pub const REDUCED_FLASHING_OPACITY: f32 = 0.4;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Accessibility {
+    pub palette: Palette,
+    pub reduce_flashing: bool,
+    //how fast the game runs, from `MIN_GAME_SPEED` up to 1
+    pub game_speed: f32,
+    pub auto_fire: bool,
+    //text from `widget` at `LARGE_TEXT_SCALE` times its size
+    pub large_text: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            palette: Palette::Standard,
+            reduce_flashing: false,
+            game_speed: 1.,
+            auto_fire: false,
+            large_text: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Palette {
+    #[default]
+    Standard,
+    //red-green
+    Deuteranopia,
+    Protanopia,
+    //blue-yellow
+    Tritanopia,
+}
+
+//multiplied into the sprites' own colours. The aliens are magenta workers, blue soldiers and
+//yellow-green queens, and both sides shoot the same grey chain
+struct Tints {
+    worker: Color,
+    soldier: Color,
+    queen: Color,
+    player_shot: Color,
+    alien_shot: Color,
+}
+
+impl Palette {
+    fn next(self) -> Self {
+        match self {
+            Palette::Standard => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::Standard,
+        }
+    }
+
+    //the key of its name in the string tables
+    fn name(self) -> &'static str {
+        match self {
+            Palette::Standard => "palette.standard",
+            Palette::Deuteranopia => "palette.deuteranopia",
+            Palette::Protanopia => "palette.protanopia",
+            Palette::Tritanopia => "palette.tritanopia",
+        }
+    }
+
+    fn tints(self) -> Tints {
+        //blue against orange stays apart for red-green deficiencies, and white against red for
+        //blue-yellow
+        let sky_blue = Color::srgb(0.35, 0.7, 1.0);
+        let orange = Color::srgb(1.0, 0.6, 0.0);
+        match self {
+            Palette::Standard => Tints {
+                worker: Color::WHITE,
+                soldier: Color::WHITE,
+                queen: Color::WHITE,
+                player_shot: Color::WHITE,
+                alien_shot: Color::WHITE,
+            },
+            //workers go red, which sets them apart from the blue soldiers by brightness
+            Palette::Deuteranopia => Tints {
+                worker: Color::srgb(1.0, 1.0, 0.25),
+                soldier: Color::WHITE,
+                queen: Color::WHITE,
+                player_shot: sky_blue,
+                alien_shot: orange,
+            },
+            //red looks dark without red cones, so workers keep a little of their blue
+            Palette::Protanopia => Tints {
+                worker: Color::srgb(1.0, 1.0, 0.55),
+                soldier: Color::WHITE,
+                queen: Color::WHITE,
+                player_shot: sky_blue,
+                alien_shot: orange,
+            },
+            //queens go green, away from the pinkish workers
+            Palette::Tritanopia => Tints {
+                worker: Color::WHITE,
+                soldier: Color::WHITE,
+                queen: Color::srgb(0.3, 1.0, 1.0),
+                player_shot: Color::WHITE,
+                alien_shot: Color::srgb(1.0, 0.3, 0.3),
+            },
+        }
+    }
+}
+
+#[derive(Component)]
+struct AccessibilityText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.5;
+
+//the keys that change an option, see `change_accessibility`
+const OPTION_KEYS: [KeyCode; 6] = [
+    KeyCode::KeyC,
+    KeyCode::KeyV,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::KeyT,
+    KeyCode::KeyZ,
+];
+
+fn change_accessibility(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+) {
+    //touching the settings marks them changed, which saves them and updates every text
+    if !keys.any_just_pressed(OPTION_KEYS) {
+        return;
+    }
+    let accessibility = &mut settings.accessibility;
+    let text = if keys.just_pressed(KeyCode::KeyC) {
+        accessibility.palette = accessibility.palette.next();
+        Localized::new(accessibility.palette.name())
+    } else if keys.just_pressed(KeyCode::KeyV) {
+        accessibility.reduce_flashing = !accessibility.reduce_flashing;
+        on_off("reduced_flashing", accessibility.reduce_flashing)
+    } else if keys.just_pressed(KeyCode::BracketLeft) || keys.just_pressed(KeyCode::BracketRight) {
+        let step = if keys.just_pressed(KeyCode::BracketLeft) {
+            -GAME_SPEED_STEP
+        } else {
+            GAME_SPEED_STEP
+        };
+        //whole steps, so repeated presses land back on full speed exactly
+        let speed = ((accessibility.game_speed + step) / GAME_SPEED_STEP).round() * GAME_SPEED_STEP;
+        accessibility.game_speed = speed.clamp(MIN_GAME_SPEED, 1.);
+        Localized::new("game_speed").arg("speed", format!("{:.0}", accessibility.game_speed * 100.))
+    } else if keys.just_pressed(KeyCode::KeyT) {
+        accessibility.auto_fire = !accessibility.auto_fire;
+        on_off("auto_fire", accessibility.auto_fire)
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        accessibility.large_text = !accessibility.large_text;
+        on_off("large_text", accessibility.large_text)
+    } else {
+        return;
+    };
+
+    commands.spawn((
+        widget::ui_center_root("Accessibility"),
+        GlobalZIndex(2),
+        AccessibilityText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(widget::large_text(""), text)],
+    ));
+}
+
+//the `.on` or `.off` key of the option
+fn on_off(option: &str, on: bool) -> Localized {
+    Localized::new(format!("{option}.{}", if on { "on" } else { "off" }))
+}
+
+fn apply_game_speed(settings: Res<Settings>, mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(settings.accessibility.game_speed.clamp(MIN_GAME_SPEED, 1.));
+}
+
+//every frame, since pooled shots and new waves come back in their own colours
+fn recolor_sprites(
+    settings: Res<Settings>,
+    mut alien_query: Query<(&Alien, &mut Sprite)>,
+    mut projectile_query: Query<&mut Sprite, (With<Projectile>, Without<Alien>)>,
+    mut alien_projectile_query: Query<
+        &mut Sprite,
+        (With<AlienProjectile>, Without<Alien>, Without<Projectile>),
+    >,
+) {
+    let tints = settings.accessibility.palette.tints();
+    for (alien, mut sprite) in alien_query.iter_mut() {
+        let tint = match alien.alien_type {
+            AlienType::Soldier => tints.soldier,
+            AlienType::Queen => tints.queen,
+            AlienType::Worker | AlienType::Empty => tints.worker,
+        };
+        tint_sprite(&mut sprite, tint);
+    }
+    for mut sprite in projectile_query.iter_mut() {
+        tint_sprite(&mut sprite, tints.player_shot);
+    }
+    for mut sprite in alien_projectile_query.iter_mut() {
+        tint_sprite(&mut sprite, tints.alien_shot);
+    }
+}
+
+fn tint_sprite(sprite: &mut Mut<Sprite>, tint: Color) {
+    if sprite.color != tint {
+        sprite.color = tint;
+    }
+}
+
+fn scale_text(settings: Res<Settings>, mut text_query: Query<(&BaseFontSize, &mut TextFont)>) {
+    let scale = if settings.accessibility.large_text {
+        LARGE_TEXT_SCALE
+    } else {
+        1.
+    };
+    for (base, mut font) in text_query.iter_mut() {
+        let size = base.0 * scale;
+        if font.font_size != size {
+            font.font_size = size;
+        }
+    }
+}
+
+fn destroy_accessibility_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut AccessibilityText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
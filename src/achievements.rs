@@ -0,0 +1,307 @@
+//! Achievements, described in `achievements/achievements.ach.ron`.
+//!
+//! Gameplay events feed progress counters for the current wave and chain, and running totals that
+//! are kept between sessions. An achievement unlocks once its [`Goal`] is met, shows a toast and
+//! is saved along with the totals. Nothing is tracked while the demo plays.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::alien::{Alien, AlienType};
+use crate::alien_projectile::PlayerKilledEvent;
+use crate::capsule::CapsuleCollisionEvent;
+use crate::fire::Fire;
+use crate::level_indicator::ScoreManager;
+use crate::localization::Localized;
+use crate::player::{Player, PlayerShootEvent};
+use crate::projectile::AlienKilledEvent;
+use crate::ron_asset::RonAssetPlugin;
+use crate::storage;
+use crate::title_screen::Screen;
+use crate::widget;
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<AchievementList>::new(&["ach.ron"]))
+            .insert_resource(AchievementRecord::load())
+            .init_resource::<Progress>()
+            .init_resource::<Toasts>()
+            .add_systems(Startup, setup_achievements)
+            .add_systems(
+                Update,
+                (
+                    (count_events, track_waves, check_achievements)
+                        .chain()
+                        .run_if(in_state(Screen::Playing)),
+                    show_toasts,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, save_achievements);
+    }
+}
+
+const LIST_PATH: &str = "achievements/achievements.ach.ron";
+const RECORD_FILE: &str = "achievements";
+const TOAST_TIME: f32 = 3.;
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct AchievementList {
+    pub achievements: Vec<Achievement>,
+}
+
+#[derive(Deserialize)]
+pub struct Achievement {
+    //saved once unlocked, so it must not change
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub goal: Goal,
+}
+
+#[derive(Deserialize)]
+pub enum Goal {
+    //a running total, kept across sessions, reaches `count`
+    Total { stat: Stat, count: i32 },
+    //one chain reaction kills at least this many aliens
+    Chain(i32),
+    //clear a wave having fired at most this many shots
+    ClearWaveWithShots(i32),
+    ClearWaveWithoutCapsule,
+    Level(i32),
+    //any player has at least this many side guns
+    SideGuns(i32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stat {
+    Kills,
+    QueenKills,
+    Shots,
+    CapsulesCaught,
+    WavesCleared,
+}
+
+//what has been unlocked and the running totals, saved between sessions
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct AchievementRecord {
+    unlocked: BTreeSet<String>,
+    totals: BTreeMap<Stat, i32>,
+    #[serde(skip)]
+    save_pending: bool,
+}
+
+impl AchievementRecord {
+    fn load() -> Self {
+        storage::load(RECORD_FILE).unwrap_or_default()
+    }
+
+    fn add(&mut self, stat: Stat, amount: i32) {
+        *self.totals.entry(stat).or_default() += amount;
+    }
+
+    fn total(&self, stat: Stat) -> i32 {
+        self.totals.get(&stat).copied().unwrap_or_default()
+    }
+}
+
+//counters for what is happening right now
+#[derive(Resource, Default)]
+struct Progress {
+    //since the current wave started
+    shots: i32,
+    capsules: i32,
+    aliens_alive: usize,
+    //set on the frame the wave is cleared, with the shots and capsules it took
+    cleared: Option<(i32, i32)>,
+    //aliens killed by the chain reaction that is still burning
+    chain: i32,
+    kills_this_frame: bool,
+}
+
+impl Progress {
+    fn start_wave(&mut self) {
+        self.shots = 0;
+        self.capsules = 0;
+    }
+}
+
+impl Goal {
+    fn met(
+        &self,
+        record: &AchievementRecord,
+        progress: &Progress,
+        level: i32,
+        side_guns: i32,
+    ) -> bool {
+        match self {
+            Goal::Total { stat, count } => record.total(*stat) >= *count,
+            Goal::Chain(count) => progress.chain >= *count,
+            Goal::ClearWaveWithShots(shots) => {
+                progress.cleared.is_some_and(|(taken, _)| taken <= *shots)
+            }
+            Goal::ClearWaveWithoutCapsule => {
+                progress.cleared.is_some_and(|(_, capsules)| capsules == 0)
+            }
+            Goal::Level(goal) => level >= *goal,
+            Goal::SideGuns(goal) => side_guns >= *goal,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct AchievementListHandle(Handle<AchievementList>);
+
+//unlocked achievements waiting for their turn to be shown
+#[derive(Resource, Default)]
+struct Toasts {
+    //the name and description of each
+    queue: VecDeque<(Localized, Localized)>,
+    time_remaining: f32,
+}
+
+#[derive(Component)]
+struct Toast;
+
+fn setup_achievements(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AchievementListHandle(asset_server.load(LIST_PATH)));
+}
+
+fn count_events(
+    mut alien_killed_events: EventReader<AlienKilledEvent>,
+    mut shoot_events: EventReader<PlayerShootEvent>,
+    mut capsule_collision_events: EventReader<CapsuleCollisionEvent>,
+    mut player_killed_events: EventReader<PlayerKilledEvent>,
+    mut record: ResMut<AchievementRecord>,
+    mut progress: ResMut<Progress>,
+) {
+    progress.kills_this_frame = false;
+    for event in alien_killed_events.read() {
+        record.add(Stat::Kills, 1);
+        if event.alien_type == AlienType::Queen {
+            record.add(Stat::QueenKills, 1);
+        }
+        progress.chain += 1;
+        progress.kills_this_frame = true;
+    }
+    for _ in shoot_events.read() {
+        record.add(Stat::Shots, 1);
+        progress.shots += 1;
+    }
+    for _ in capsule_collision_events.read() {
+        record.add(Stat::CapsulesCaught, 1);
+        progress.capsules += 1;
+    }
+    //the run starts over, and so does the wave
+    if player_killed_events.read().count() > 0 {
+        progress.start_wave();
+        record.save_pending = true;
+    }
+}
+
+fn track_waves(
+    alien_query: Query<&Alien>,
+    fire_query: Query<(), With<Fire>>,
+    mut record: ResMut<AchievementRecord>,
+    mut progress: ResMut<Progress>,
+) {
+    let alive = alien_query.iter().filter(|alien| !alien.dead).count();
+    if progress.aliens_alive > 0 && alive == 0 {
+        progress.cleared = Some((progress.shots, progress.capsules));
+        record.add(Stat::WavesCleared, 1);
+        record.save_pending = true;
+    } else if progress.aliens_alive == 0 && alive > 0 {
+        progress.start_wave();
+    }
+    progress.aliens_alive = alive;
+
+    //a chain goes on for as long as the fires it started keep burning
+    if !progress.kills_this_frame && fire_query.is_empty() {
+        progress.chain = 0;
+    }
+}
+
+fn check_achievements(
+    list_handle: Res<AchievementListHandle>,
+    lists: Res<Assets<AchievementList>>,
+    score_manager: Res<ScoreManager>,
+    player_query: Query<&Player>,
+    mut record: ResMut<AchievementRecord>,
+    mut progress: ResMut<Progress>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if let Some(list) = lists.get(&list_handle.0) {
+        let side_guns = player_query
+            .iter()
+            .map(|player| player.side_gun_projectiles)
+            .max()
+            .unwrap_or_default();
+
+        for achievement in &list.achievements {
+            if record.unlocked.contains(&achievement.id)
+                || !achievement
+                    .goal
+                    .met(&record, &progress, score_manager.curr_level, side_guns)
+            {
+                continue;
+            }
+            record.unlocked.insert(achievement.id.clone());
+            record.save_pending = true;
+            //the list's own words for languages that don't translate them
+            let id = &achievement.id;
+            toasts.queue.push_back((
+                Localized::new(format!("achievement.{id}.name")).or(&achievement.name),
+                Localized::new(format!("achievement.{id}.description"))
+                    .or(&achievement.description),
+            ));
+        }
+    }
+
+    progress.cleared = None;
+}
+
+fn show_toasts(
+    mut commands: Commands,
+    mut toasts: ResMut<Toasts>,
+    toast_query: Query<Entity, With<Toast>>,
+    time: Res<Time<Real>>,
+) {
+    toasts.time_remaining -= time.delta_secs();
+    if toasts.time_remaining > 0. {
+        return;
+    }
+    for entity in toast_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let Some((name, description)) = toasts.queue.pop_front() else {
+        return;
+    };
+    toasts.time_remaining = TOAST_TIME;
+    commands.spawn((
+        widget::ui_toast_root("Achievement"),
+        GlobalZIndex(4),
+        Toast,
+        children![
+            (
+                widget::small_text(""),
+                Localized::new("achievement_unlocked")
+            ),
+            (widget::large_text(""), name),
+            (widget::small_text(""), description),
+        ],
+    ));
+}
+
+fn save_achievements(mut record: ResMut<AchievementRecord>, mut exit_events: EventReader<AppExit>) {
+    let exiting = exit_events.read().count() > 0;
+    if record.save_pending || exiting {
+        record.save_pending = false;
+        storage::save(RECORD_FILE, &*record);
+    }
+}
@@ -0,0 +1,383 @@
+//! Adaptive background music.
+//!
+//! Each [`MusicTrack`] is split by channel into layers that play in step with each other. Each
+//! layer fades in or out with the intensity of the game, every layer speeds up together with the
+//! aliens, and short stingers play over the top when a level is cleared or the player dies.
+
+use std::time::Duration;
+
+use bevy::{asset::io::embedded::EmbeddedAssetRegistry, audio::Volume, prelude::*};
+use bevy_rustysynth::MidiAudio;
+use serde::Deserialize;
+
+use crate::{
+    alien::{ALIEN_SPEED_INCREMENT, Alien, INITIAL_ALIEN_SPEED, SpeedChangedEvent},
+    alien_projectile::PlayerKilledEvent,
+    fire::Fire,
+    level_indicator::LevelCompletedEvent,
+    midi::{self, MidiSource, MidiSourcePlugin},
+    resolution,
+};
+
+pub struct AdaptiveMusicPlugin;
+
+impl Plugin for AdaptiveMusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MidiSourcePlugin)
+            .init_resource::<MusicIntensity>()
+            .add_systems(Startup, setup_music)
+            .add_systems(
+                Update,
+                (
+                    split_layers,
+                    spawn_layers,
+                    update_intensity,
+                    speed_changed,
+                    play_stingers,
+                    update_tracks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MusicLayer {
+    pub channels: Vec<u8>,
+    //volume of the layer while the game is calm
+    #[serde(default = "default_quiet_volume")]
+    pub quiet_volume: f32,
+    //the layer starts fading in above `quiet_until` intensity and is at full volume from `full_from`
+    #[serde(default)]
+    pub quiet_until: f32,
+    #[serde(default)]
+    pub full_from: f32,
+}
+
+fn default_quiet_volume() -> f32 {
+    1.
+}
+
+//a playing song. its layers are spawned as children once the midi has loaded, and a track without
+//layers plays the whole song at full volume
+#[derive(Component)]
+pub struct MusicTrack {
+    pub source: Handle<MidiAudio>,
+    //the same file, which is split into layers
+    pub smf: Handle<MidiSource>,
+    //where the file was loaded from, which also names its layers
+    pub path: String,
+    pub layers: Vec<MusicLayer>,
+    pub volume: f32,
+    //multiplies the tempo the aliens set
+    pub speed: f32,
+    //the track fades towards `fade_target` and is despawned once it has faded out
+    pub fade: f32,
+    pub fade_target: f32,
+}
+
+impl MusicTrack {
+    pub fn new(
+        asset_server: &AssetServer,
+        midi: &str,
+        layers: Vec<MusicLayer>,
+        volume: f32,
+        speed: f32,
+    ) -> Self {
+        Self {
+            source: asset_server.load(midi.to_owned()),
+            smf: asset_server.load(midi.to_owned()),
+            path: midi.to_owned(),
+            layers,
+            volume,
+            speed,
+            fade: 0.,
+            fade_target: 1.,
+        }
+    }
+}
+
+//seconds it takes a track to fade in or out
+pub const CROSSFADE_TIME: f32 = 2.0;
+
+//how much each part of the game contributes to the intensity
+const CLEARED_WEIGHT: f32 = 0.45;
+const DANGER_WEIGHT: f32 = 0.35;
+const FIRE_WEIGHT: f32 = 0.2;
+//the number of burning fires that counts as a fully intense chain reaction
+const FIRES_FOR_FULL_INTENSITY: f32 = 10.;
+//how quickly the mix and tempo follow the game, higher is faster
+const INTENSITY_SMOOTHING: f32 = 1.5;
+const TEMPO_SMOOTHING: f32 = 2.0;
+
+const SPEED_FACTOR: f32 = 0.02;
+const MAX_SPEED: f32 = 2.0;
+
+//the music ducks under a stinger for this long
+const STINGER_DUCK_TIME: f32 = 1.2;
+const STINGER_DUCK_VOLUME: f32 = 0.35;
+const STINGER_NOTE_LENGTH: Duration = Duration::from_millis(110);
+const STINGER_VELOCITY: u8 = 110;
+//synth lead, rising major arpeggio
+const LEVEL_CLEARED_STINGER: (u8, [u8; 4]) = (80, [72, 76, 79, 84]);
+//synth lead, falling minor arpeggio
+const PLAYER_KILLED_STINGER: (u8, [u8; 4]) = (80, [67, 63, 60, 55]);
+
+//0 while the game is calm, 1 while a wave is nearly cleared, close to the player or on fire
+#[derive(Resource, Default)]
+pub struct MusicIntensity {
+    pub target: f32,
+    pub current: f32,
+}
+
+#[derive(Resource)]
+struct MusicState {
+    level_cleared_stinger: Handle<MidiAudio>,
+    player_killed_stinger: Handle<MidiAudio>,
+    target_speed: f32,
+    speed: f32,
+    duck_timer: f32,
+}
+
+//plays one layer of its parent track, or the whole track when `layer` is `None`
+#[derive(Component)]
+struct MusicLayerPlayer {
+    layer: Option<usize>,
+}
+
+//the audio of each layer of a track, waiting to finish loading
+#[derive(Component)]
+struct LayerAudio(Vec<(Handle<MidiAudio>, Option<usize>)>);
+
+#[derive(Component)]
+struct LayersSpawned;
+
+fn setup_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<EmbeddedAssetRegistry>,
+) {
+    commands.insert_resource(MusicState {
+        level_cleared_stinger: stinger(
+            &asset_server,
+            &registry,
+            "level_cleared",
+            LEVEL_CLEARED_STINGER,
+        ),
+        player_killed_stinger: stinger(
+            &asset_server,
+            &registry,
+            "player_killed",
+            PLAYER_KILLED_STINGER,
+        ),
+        target_speed: 1.,
+        speed: 1.,
+        duck_timer: 0.,
+    });
+}
+
+fn stinger(
+    asset_server: &AssetServer,
+    registry: &EmbeddedAssetRegistry,
+    name: &str,
+    (preset, keys): (u8, [u8; 4]),
+) -> Handle<MidiAudio> {
+    let smf = midi::note_sequence(preset, &keys, STINGER_VELOCITY, STINGER_NOTE_LENGTH);
+    midi::load_generated(asset_server, registry, &format!("stinger_{name}"), smf)
+}
+
+//split each track into layers once its file has loaded
+fn split_layers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<EmbeddedAssetRegistry>,
+    track_query: Query<(Entity, &MusicTrack), Without<LayerAudio>>,
+    sources: Res<Assets<MidiSource>>,
+) {
+    for (entity, track) in track_query.iter() {
+        let mut layers = Vec::new();
+        if !track.layers.is_empty() {
+            let Some(MidiSource(smf)) = sources.get(&track.smf) else {
+                continue;
+            };
+            for (index, layer) in track.layers.iter().enumerate() {
+                match midi::filter_channels(smf, &layer.channels) {
+                    Some(layer_smf) => {
+                        //the same channels of a track always make the same layer, so they reuse
+                        //one path instead of piling up a new file each time the track plays
+                        let channels: Vec<String> =
+                            layer.channels.iter().map(u8::to_string).collect();
+                        let name = format!(
+                            "layer_{}_{}",
+                            track.path.replace('/', "_"),
+                            channels.join("_")
+                        );
+                        layers.push((
+                            midi::load_generated(&asset_server, &registry, &name, layer_smf),
+                            Some(index),
+                        ));
+                    }
+                    None => {
+                        warn!("could not split music into layers, playing it whole");
+                        layers.clear();
+                        break;
+                    }
+                }
+            }
+        }
+
+        if layers.is_empty() {
+            layers.push((track.source.clone(), None));
+        }
+        commands.entity(entity).insert(LayerAudio(layers));
+    }
+}
+
+//all of a track's layers are spawned together once they have loaded so they stay in step
+fn spawn_layers(
+    mut commands: Commands,
+    track_query: Query<(Entity, &LayerAudio), Without<LayersSpawned>>,
+    midi_assets: Res<Assets<MidiAudio>>,
+) {
+    for (entity, layer_audio) in track_query.iter() {
+        if !layer_audio
+            .0
+            .iter()
+            .all(|(handle, _)| midi_assets.contains(handle))
+        {
+            continue;
+        }
+        commands.entity(entity).insert(LayersSpawned);
+
+        for (handle, layer) in layer_audio.0.iter() {
+            commands.entity(entity).with_child((
+                AudioPlayer(handle.clone()),
+                PlaybackSettings {
+                    mode: bevy::audio::PlaybackMode::Loop,
+                    volume: Volume::Linear(0.),
+                    ..default()
+                },
+                MusicLayerPlayer { layer: *layer },
+            ));
+        }
+    }
+}
+
+fn update_intensity(
+    mut intensity: ResMut<MusicIntensity>,
+    alien_query: Query<(&Alien, &Transform)>,
+    fire_query: Query<(), With<Fire>>,
+    resolution: Res<resolution::Resolution>,
+    time: Res<Time>,
+) {
+    let mut total = 0;
+    let mut alive = 0;
+    let mut lowest = f32::MAX;
+    for (alien, transform) in alien_query.iter() {
+        total += 1;
+        if !alien.dead {
+            alive += 1;
+            lowest = lowest.min(transform.translation.y);
+        }
+    }
+    if total == 0 {
+        return;
+    }
+
+    let cleared = 1. - alive as f32 / total as f32;
+    //the formation starts above the middle of the screen and the player dies when it reaches the bottom
+    let half_height = resolution.screen_dimensions.y * 0.5;
+    let danger = if alive > 0 {
+        1. - ((lowest + half_height) / half_height).clamp(0., 1.)
+    } else {
+        0.
+    };
+    let fires = (fire_query.iter().count() as f32 / FIRES_FOR_FULL_INTENSITY).min(1.);
+
+    intensity.target =
+        (CLEARED_WEIGHT * cleared + DANGER_WEIGHT * danger + FIRE_WEIGHT * fires).clamp(0., 1.);
+    let blend = 1. - (-INTENSITY_SMOOTHING * time.delta_secs()).exp();
+    intensity.current += (intensity.target - intensity.current) * blend;
+}
+
+fn speed_changed(mut events: EventReader<SpeedChangedEvent>, mut state: ResMut<MusicState>) {
+    for speed_changed in events.read() {
+        let num_speed_increments =
+            (speed_changed.speed - INITIAL_ALIEN_SPEED) / ALIEN_SPEED_INCREMENT;
+
+        state.target_speed = (1. + (num_speed_increments * SPEED_FACTOR)).min(MAX_SPEED);
+    }
+}
+
+fn play_stingers(
+    mut commands: Commands,
+    mut level_completed_events: EventReader<LevelCompletedEvent>,
+    mut player_killed_events: EventReader<PlayerKilledEvent>,
+    mut state: ResMut<MusicState>,
+) {
+    //dying also restarts the level, so the death stinger wins when both happen together
+    let stinger = if player_killed_events.read().count() > 0 {
+        level_completed_events.clear();
+        state.player_killed_stinger.clone()
+    } else if level_completed_events.read().count() > 0 {
+        state.level_cleared_stinger.clone()
+    } else {
+        return;
+    };
+
+    commands.spawn((AudioPlayer(stinger), PlaybackSettings::DESPAWN));
+    state.duck_timer = STINGER_DUCK_TIME;
+}
+
+fn update_tracks(
+    mut commands: Commands,
+    mut state: ResMut<MusicState>,
+    intensity: Res<MusicIntensity>,
+    mut track_query: Query<(Entity, &mut MusicTrack, Option<&Children>)>,
+    mut layer_query: Query<(&MusicLayerPlayer, &mut AudioSink)>,
+    time: Res<Time>,
+) {
+    let blend = 1. - (-TEMPO_SMOOTHING * time.delta_secs()).exp();
+    state.speed += (state.target_speed - state.speed) * blend;
+    state.duck_timer -= time.delta_secs();
+    let duck = if state.duck_timer > 0. {
+        STINGER_DUCK_VOLUME
+    } else {
+        1.
+    };
+
+    for (entity, mut track, children) in track_query.iter_mut() {
+        let step = time.delta_secs() / CROSSFADE_TIME;
+        track.fade = if track.fade < track.fade_target {
+            (track.fade + step).min(track.fade_target)
+        } else {
+            (track.fade - step).max(track.fade_target)
+        };
+        if track.fade <= 0. && track.fade_target <= 0. {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        for child in children.into_iter().flatten() {
+            let Ok((player, mut sink)) = layer_query.get_mut(*child) else {
+                continue;
+            };
+            let gain = match player.layer.and_then(|index| track.layers.get(index)) {
+                Some(layer) => layer_gain(layer, intensity.current),
+                None => 1.,
+            };
+
+            sink.set_volume(Volume::Linear(track.volume * track.fade * gain * duck));
+            sink.set_speed(state.speed * track.speed);
+        }
+    }
+}
+
+fn layer_gain(layer: &MusicLayer, intensity: f32) -> f32 {
+    let fade = if layer.full_from > layer.quiet_until {
+        ((intensity - layer.quiet_until) / (layer.full_from - layer.quiet_until)).clamp(0., 1.)
+    } else {
+        1.
+    };
+    layer.quiet_volume + (1. - layer.quiet_volume) * fade
+}
@@ -1,17 +1,39 @@
 use bevy::prelude::*;
+use serde::Serialize;
 
 use crate::alien_layouts::*;
-use crate::alien_projectile::PlayerKilledEvent;
+use crate::alien_projectile::{DeathCause, PlayerKilledEvent};
+use crate::collider::{Collider, Layers};
+use crate::console::{self, ConsoleAppExt};
+use crate::difficulty::Difficulty;
+use crate::fly_in::FlyingIn;
+use crate::knockback::Knockback;
 use crate::level_indicator::LevelCompletedEvent;
 use crate::resolution;
+use crate::wave_file;
 
 pub struct AlienPlugin;
 
 impl Plugin for AlienPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (setup_aliens, setup_wave))
-            .add_systems(Update, (update_aliens, manage_alien_logic, player_killed));
+        app.add_systems(Startup, setup_aliens).add_systems(
+            Update,
+            (
+                (
+                    update_aliens,
+                    manage_alien_logic,
+                    spawn_formation.run_if(resource_changed::<Formation>),
+                    restore_wave.run_if(resource_exists::<RestoreWave>),
+                )
+                    .chain(),
+                player_killed,
+                apply_difficulty.run_if(resource_changed::<Difficulty>),
+            ),
+        );
         app.add_event::<SpeedChangedEvent>();
+        app.add_event::<WaveStartedEvent>();
+        app.add_console_command("speed <n>", set_speed)
+            .add_console_command("wave load <path>", load_wave);
     }
 }
 
@@ -20,6 +42,10 @@ pub struct SpeedChangedEvent {
     pub speed: f32,
 }
 
+//the formation is back at the top, for a new wave or after the player has died
+#[derive(Event)]
+pub struct WaveStartedEvent {}
+
 #[derive(Component)]
 pub struct Alien {
     pub dead: bool,
@@ -27,7 +53,7 @@ pub struct Alien {
     pub alien_type: AlienType,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AlienType {
     Worker,
     Soldier,
@@ -35,12 +61,23 @@ pub enum AlienType {
     Empty,
 }
 
+impl AlienType {
+    pub fn points(self) -> i32 {
+        match self {
+            AlienType::Worker => 10,
+            AlienType::Soldier => 20,
+            AlienType::Queen => 50,
+            AlienType::Empty => 0,
+        }
+    }
+}
+
 //a marker component to prevent querying any dead aliens in our updates after they have already died
 #[derive(Component)]
 pub struct Dead;
 
 //controls the behavior of our aliens
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct AlienManager {
     pub direction: f32,
     //we increment the aliens vertically when this is true once
@@ -50,13 +87,99 @@ pub struct AlienManager {
     //the game will reset when this is triggered
     pub reset: bool,
     pub speed: f32,
+    //how many times the formation has sped up this wave
+    pub speed_ups: i32,
     pub prev_alien_count: i32,
     pub reset_cooldown: f32,
 }
 
+//the layout of the wave. changing it replaces every alien with the new formation
+#[derive(Resource, PartialEq)]
+pub struct Formation {
+    pub mask: AlienMask,
+    //the distance between neighbouring cells of the mask
+    pub spacing: Vec2,
+}
+
+impl Default for Formation {
+    fn default() -> Self {
+        Self {
+            mask: DEFAULT_MASK,
+            spacing: DEFAULT_SPACING,
+        }
+    }
+}
+
+impl Formation {
+    //where the alien in the cell starts the wave
+    pub fn position(&self, row: usize, col: usize) -> Vec2 {
+        let half_width = ALIEN_COLS as f32 * self.spacing.x * 0.5;
+        Vec2::new(
+            col as f32 * self.spacing.x - half_width,
+            row as f32 * self.spacing.y + VERT_OFFSET,
+        )
+    }
+}
+
+//everything needed to put a wave back the way it was left
+#[derive(Clone)]
+pub struct WaveSnapshot {
+    pub manager: AlienManager,
+    pub mask: AlienMask,
+    //how far the formation has moved from where it started
+    pub offset: [f32; 3],
+    //whether each alien is dead, ordered bottom row first and then left to right
+    pub dead: Vec<bool>,
+}
+
+impl WaveSnapshot {
+    pub fn new<'a>(
+        alien_manager: &AlienManager,
+        formation: &Formation,
+        aliens: impl Iterator<Item = (&'a Alien, &'a Transform)>,
+    ) -> Self {
+        let mut aliens: Vec<(&Alien, &Transform)> = aliens.collect();
+        sort_aliens(&mut aliens, |(alien, _)| alien);
+
+        //dead aliens stop moving, so the offset comes from one that is still alive
+        let offset = aliens
+            .iter()
+            .find(|(alien, _)| !alien.dead)
+            .map(|(alien, transform)| transform.translation - alien.original_position)
+            .unwrap_or_default();
+
+        Self {
+            manager: AlienManager {
+                reset: false,
+                shift_aliens_down: false,
+                ..alien_manager.clone()
+            },
+            mask: formation.mask,
+            offset: offset.to_array(),
+            dead: aliens.iter().map(|(alien, _)| alien.dead).collect(),
+        }
+    }
+}
+
+//insert to put the wave back the way a snapshot left it, replacing the formation if need be
+#[derive(Resource)]
+pub struct RestoreWave(pub WaveSnapshot);
+
+fn sort_aliens<T>(aliens: &mut [T], alien: impl Fn(&T) -> &Alien) {
+    aliens.sort_by(|a, b| {
+        let a = alien(a).original_position;
+        let b = alien(b).original_position;
+        a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x))
+    });
+}
+
 //width and height represent the amount of aliens horizontally and vertically which we wish to spawn
-const HORIZ_SPACING: f32 = 22.;
-const VERT_SPACING: f32 = 50.0;
+pub const DEFAULT_SPACING: Vec2 = Vec2::new(22., 50.);
+//a little smaller than the 32 pixel sprites, which have a border around the alien
+//aliens marching through a bunker wear it away
+pub const COLLIDER: Collider = Collider::rect(18., 16.)
+    .on(Layers::ALIEN)
+    .hits(Layers::BUNKER);
 pub const INITIAL_ALIEN_SPEED: f32 = 35.0;
 pub const ALIEN_SPEED_INCREMENT: f32 = 12.0;
 const ALIEN_SHIFT_AMOUNT: f32 = 16.;
@@ -64,32 +187,39 @@ const ZINDEX: f32 = 15.0;
 const VERT_OFFSET: f32 = 70.0;
 
 //spawn our aliens
-fn setup_aliens(mut commands: Commands) {
+fn setup_aliens(mut commands: Commands, difficulty: Res<Difficulty>) {
     commands.insert_resource(AlienManager {
         reset: false,
         dist_from_boundary: 0.,
         shift_aliens_down: false,
         direction: 1.,
-        speed: INITIAL_ALIEN_SPEED,
+        speed: difficulty.initial_alien_speed,
+        speed_ups: 0,
         prev_alien_count: 99,
         reset_cooldown: 0.,
     });
+    commands.insert_resource(Formation::default());
 }
 
 #[allow(clippy::needless_range_loop)]
-fn setup_wave(
+pub fn spawn_formation(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     resolution: Res<resolution::Resolution>,
+    formation: Res<Formation>,
+    alien_query: Query<Entity, With<Alien>>,
 ) {
+    for entity in alien_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
     let worker_texture = asset_server.load("images/alien_worker.png");
     let soldier_texture = asset_server.load("images/alien_soldier.png");
     let queen_texture = asset_server.load("images/alien_queen.png");
-    let half_width = ALIEN_COLS as f32 * HORIZ_SPACING * 0.5;
 
     for row in 0..ALIEN_ROWS {
         for col in 0..ALIEN_COLS {
-            let mask_value = DEFAULT_MASK[row][col];
+            let mask_value = formation.mask[row][col];
             let alien_type;
             let mut alien_image: Handle<Image> = worker_texture.clone();
             match mask_value {
@@ -109,11 +239,7 @@ fn setup_wave(
             }
 
             if alien_type != AlienType::Empty {
-                let position = Vec3::new(
-                    col as f32 * HORIZ_SPACING - half_width,
-                    row as f32 * VERT_SPACING + VERT_OFFSET,
-                    ZINDEX,
-                );
+                let position = formation.position(row, col).extend(ZINDEX);
                 // let position = Vec3::new(col as f32 * SPACING, row as f32 * SPACING, ZINDEX)
                 // - (Vec3::X * ALIEN_COLS as f32 * 0.5);
                 //  - (Vec3::Y * ALIEN_ROWS as f32 * 1.0)
@@ -128,8 +254,9 @@ fn setup_wave(
                     Alien {
                         original_position: position,
                         dead: false,
-                        alien_type: AlienType::Worker,
+                        alien_type,
                     },
+                    COLLIDER,
                 ));
             }
         }
@@ -139,26 +266,44 @@ fn setup_wave(
 fn update_aliens(
     mut commands: Commands,
     //only query aliens that are still alive
-    mut alien_query: Query<(Entity, &Alien, &mut Transform, &mut Visibility), Without<Dead>>,
+    mut alien_query: Query<
+        (
+            Entity,
+            &Alien,
+            &mut Transform,
+            &mut Visibility,
+            Has<FlyingIn>,
+            Option<&Knockback>,
+        ),
+        Without<Dead>,
+    >,
     mut alien_manager: ResMut<AlienManager>,
     mut player_killed_events: EventWriter<PlayerKilledEvent>,
     mut level_completed_events: EventWriter<LevelCompletedEvent>,
     mut speed_changed_events: EventWriter<SpeedChangedEvent>,
     resolution: Res<resolution::Resolution>,
+    difficulty: Res<Difficulty>,
     time: Res<Time>,
 ) {
     let margin = resolution.screen_dimensions.x * 0.5 - (resolution.pixel_ratio * 25.0);
     let mut alien_alive = false;
     let mut alien_count = 0;
     alien_manager.reset_cooldown -= time.delta_secs();
-    for (entity, alien, mut transform, mut visibility) in alien_query.iter_mut() {
-        //delta_seconds makes it so our aliens move at the same speed regardless of framerate; delta_seconds() gives the time between each frame.
-        transform.translation.x +=
-            time.delta_secs() * alien_manager.direction * alien_manager.speed;
-        if transform.translation.x.abs() > margin {
-            alien_manager.shift_aliens_down = true;
-            alien_manager.dist_from_boundary =
-                margin * alien_manager.direction - transform.translation.x;
+    //the formation holds still while the wave flies in
+    let holding = alien_query.iter().any(|(.., flying, _)| flying);
+    for (entity, alien, mut transform, mut visibility, flying, knockback) in alien_query.iter_mut()
+    {
+        //an alien knocked out of formation still marches with it, from its place
+        let offset = knockback.map_or(Vec2::ZERO, |knockback| knockback.offset);
+        if !holding {
+            //delta_seconds makes it so our aliens move at the same speed regardless of framerate; delta_seconds() gives the time between each frame.
+            transform.translation.x +=
+                time.delta_secs() * alien_manager.direction * alien_manager.speed;
+            let place_x = transform.translation.x - offset.x;
+            if place_x.abs() > margin {
+                alien_manager.shift_aliens_down = true;
+                alien_manager.dist_from_boundary = margin * alien_manager.direction - place_x;
+            }
         }
 
         if alien.dead {
@@ -169,9 +314,13 @@ fn update_aliens(
         }
 
         //if the aliens have made it out of the bottom of the screen we have lost the game and should reset
-        if transform.translation.y < -resolution.screen_dimensions.y * 0.5 + 70. {
+        if !flying
+            && transform.translation.y - offset.y < -resolution.screen_dimensions.y * 0.5 + 70.
+        {
             alien_manager.reset = true;
-            player_killed_events.write(PlayerKilledEvent {});
+            player_killed_events.write(PlayerKilledEvent {
+                cause: DeathCause::Landed,
+            });
         }
 
         alien_alive = true;
@@ -191,7 +340,8 @@ fn update_aliens(
         || (alien_count < 10 && alien_manager.prev_alien_count >= 10)
         || (alien_count < 3 && alien_manager.prev_alien_count >= 3)
     {
-        alien_manager.speed += ALIEN_SPEED_INCREMENT;
+        alien_manager.speed_ups += 1;
+        alien_manager.speed = alien_speed(&alien_manager, &difficulty);
         speed_changed_events.write(SpeedChangedEvent {
             speed: alien_manager.speed,
         });
@@ -200,10 +350,13 @@ fn update_aliens(
     alien_manager.prev_alien_count = alien_count;
 }
 
-fn manage_alien_logic(
+pub fn manage_alien_logic(
     mut commands: Commands,
     mut alien_query: Query<(Entity, &mut Alien, &mut Transform)>,
     mut alien_manager: ResMut<AlienManager>,
+    mut speed_changed_events: EventWriter<SpeedChangedEvent>,
+    mut wave_started_events: EventWriter<WaveStartedEvent>,
+    difficulty: Res<Difficulty>,
 ) {
     if alien_manager.shift_aliens_down {
         //reverse direction and move aliens downward
@@ -218,8 +371,16 @@ fn manage_alien_logic(
     if alien_manager.reset {
         alien_manager.reset = false;
         alien_manager.direction = 1.;
+        //every wave starts at the speed of the level being played
+        alien_manager.speed_ups = 0;
+        alien_manager.speed = alien_speed(&alien_manager, &difficulty);
+        speed_changed_events.write(SpeedChangedEvent {
+            speed: alien_manager.speed,
+        });
+        wave_started_events.write(WaveStartedEvent {});
         for (entity, mut alien, mut transform) in alien_query.iter_mut() {
             transform.translation = alien.original_position;
+            commands.entity(entity).remove::<Knockback>();
             if alien.dead {
                 //revive our alien from the dead unit pool
                 alien.dead = false;
@@ -231,14 +392,82 @@ fn manage_alien_logic(
 
 fn player_killed(
     mut player_killed_events: EventReader<PlayerKilledEvent>,
-    mut speed_changed_events: EventWriter<SpeedChangedEvent>,
     mut alien_manager: ResMut<AlienManager>,
 ) {
     for _ in player_killed_events.read() {
         alien_manager.reset = true;
-        alien_manager.speed = INITIAL_ALIEN_SPEED;
-        speed_changed_events.write(SpeedChangedEvent {
-            speed: alien_manager.speed,
-        });
     }
 }
+
+fn alien_speed(alien_manager: &AlienManager, difficulty: &Difficulty) -> f32 {
+    difficulty.initial_alien_speed
+        + alien_manager.speed_ups as f32 * difficulty.alien_speed_increment
+}
+
+//the difficulty only changes as a wave starts, see `difficulty::update_difficulty`, which leaves
+//the formation flying in at its new speed
+fn apply_difficulty(
+    difficulty: Res<Difficulty>,
+    mut alien_manager: ResMut<AlienManager>,
+    mut speed_changed_events: EventWriter<SpeedChangedEvent>,
+) {
+    alien_manager.speed = alien_speed(&alien_manager, &difficulty);
+    speed_changed_events.write(SpeedChangedEvent {
+        speed: alien_manager.speed,
+    });
+}
+
+fn restore_wave(
+    mut commands: Commands,
+    restore: Res<RestoreWave>,
+    mut alien_query: Query<(Entity, &mut Alien, &mut Transform)>,
+    mut alien_manager: ResMut<AlienManager>,
+    mut formation: ResMut<Formation>,
+    mut speed_changed_events: EventWriter<SpeedChangedEvent>,
+) {
+    let snapshot = &restore.0;
+    if formation.mask != snapshot.mask {
+        //`spawn_formation` replaces the aliens next frame, and then we can restore them
+        formation.mask = snapshot.mask;
+        return;
+    }
+    let mut aliens: Vec<_> = alien_query.iter_mut().collect();
+    if aliens.len() != snapshot.dead.len() {
+        return;
+    }
+    commands.remove_resource::<RestoreWave>();
+
+    *alien_manager = snapshot.manager.clone();
+    speed_changed_events.write(SpeedChangedEvent {
+        speed: alien_manager.speed,
+    });
+
+    sort_aliens(&mut aliens, |(_, alien, _)| alien);
+    let offset = Vec3::from_array(snapshot.offset);
+    for ((entity, alien, transform), dead) in aliens.iter_mut().zip(snapshot.dead.iter()) {
+        transform.translation = alien.original_position + offset;
+        //`update_aliens` marks and hides the dead ones again
+        alien.dead = *dead;
+        commands
+            .entity(*entity)
+            .remove::<(Dead, FlyingIn, Knockback)>();
+    }
+}
+
+//the formation moves at this speed until it next speeds up or the wave starts over
+fn set_speed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let speed: f32 = console::arg(args, 0, "speed")?;
+    world.resource_mut::<AlienManager>().speed = speed;
+    world.send_event(SpeedChangedEvent { speed });
+    Ok(format!("alien speed {speed}"))
+}
+
+//replaces the wave with one from a file under `assets`
+fn load_wave(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let (Some(&"load"), Some(path)) = (args.first(), args.get(1)) else {
+        return Err(String::from("usage: wave load <path>"));
+    };
+    *world.resource_mut::<Formation>() = wave_file::load(path)?;
+    world.resource_mut::<AlienManager>().reset = true;
+    Ok(format!("loaded {path}"))
+}
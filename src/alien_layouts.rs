@@ -1,8 +1,11 @@
 pub const ALIEN_ROWS: usize = 5;
 pub const ALIEN_COLS: usize = 20;
 
+// 0 is an empty slot, 1 a worker, 2 a soldier and 3 a queen. The first row is the bottom of the formation.
+pub type AlienMask = [[i32; ALIEN_COLS]; ALIEN_ROWS];
+
 // Defines where the aliens should appear
-pub const DEFAULT_MASK: [[i32; ALIEN_COLS]; ALIEN_ROWS] = [
+pub const DEFAULT_MASK: AlienMask = [
     [1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0],
     [1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0],
     [1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0],
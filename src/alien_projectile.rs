@@ -1,11 +1,17 @@
 use bevy::prelude::*;
 
 use rand::seq::IteratorRandom;
+use serde::Serialize;
 
 use crate::alien::Alien;
 use crate::alien::Dead;
+use crate::collider::{Collider, Layers};
+use crate::difficulty::Difficulty;
+use crate::fly_in::FlyingIn;
 use crate::player;
+use crate::pool::{Pool, PoolAppExt, Textures};
 use crate::resolution;
+use crate::rng::GameRng;
 
 pub struct AlienProjectilePlugin;
 
@@ -20,20 +26,41 @@ impl Plugin for AlienProjectilePlugin {
             ),
         );
         app.add_event::<PlayerKilledEvent>();
+        app.add_event::<PlayerHitEvent>();
         app.add_event::<AlienShootEvent>();
+        app.add_pool::<AlienProjectile>(POOL_SIZE);
     }
 }
 
+const POOL_SIZE: usize = 16;
+
 #[derive(Component)]
 struct AlienProjectileCooldown {
     shoot_timer: f32,
 }
 
-const SHOOT_COOLDOWN: f32 = 1.2;
-const BULLET_SPEED: f32 = 240.;
+pub const SHOOT_COOLDOWN: f32 = 1.2;
+pub const BULLET_SPEED: f32 = 240.;
 
+//every player is out of lives, or the aliens have landed, and the run starts over
 #[derive(Event, Debug)]
-pub struct PlayerKilledEvent {}
+pub struct PlayerKilledEvent {
+    pub cause: DeathCause,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeathCause {
+    //the last player with lives left was shot
+    Shot,
+    //the formation reached the bottom of the screen
+    Landed,
+}
+
+//a player lost a life, but someone is still playing
+#[derive(Event, Debug)]
+pub struct PlayerHitEvent {
+    pub player: Entity,
+}
 
 #[derive(Event, Debug)]
 pub struct AlienShootEvent {}
@@ -51,64 +78,97 @@ fn setup_cooldown(mut commands: Commands) {
 fn update_alien_projectiles(
     mut commands: Commands,
     mut projectile_query: Query<(Entity, &AlienProjectile, &mut Transform)>,
+    mut pool: ResMut<Pool<AlienProjectile>>,
     time: Res<Time>,
     resolution: Res<resolution::Resolution>,
 ) {
     for (entity, alien_projectile, mut transform) in projectile_query.iter_mut() {
         transform.translation.y -= alien_projectile.speed * time.delta_secs();
         if transform.translation.y < -resolution.screen_dimensions.y * 0.5 {
-            commands.entity(entity).despawn();
+            pool.release(&mut commands, entity);
         }
     }
 }
 
-const BULLET_RADIUS: f32 = 10.;
+pub const COLLIDER: Collider = Collider::circle(4.)
+    .on(Layers::ALIEN_SHOT)
+    .hits(Layers::PLAYER.union(Layers::BUNKER));
 
 fn update_player_interactions(
-    mut player_query: Query<(&mut player::Player, &Transform)>,
-    mut alien_projectile_query: Query<(Entity, &Transform), With<AlienProjectile>>,
+    mut player_query: Query<(Entity, &mut player::Player, &Collider, &Transform)>,
+    //waiting players are off screen, and invincible ones are cheating
+    untouchable_query: Query<(), Or<(With<player::AwaitingTurn>, With<player::Invincible>)>>,
+    mut alien_projectile_query: Query<(Entity, &Collider, &Transform), With<AlienProjectile>>,
     mut commands: Commands,
-    mut events: EventWriter<PlayerKilledEvent>,
+    mut pool: ResMut<Pool<AlienProjectile>>,
+    mut player_killed_events: EventWriter<PlayerKilledEvent>,
+    mut player_hit_events: EventWriter<PlayerHitEvent>,
 ) {
-    let (mut player, player_transform) = player_query.single_mut().unwrap();
-    for (alien_projectile_entity, alien_projectile_transform) in alien_projectile_query.iter_mut() {
-        let alien_projectile_pos = Vec2::new(
-            alien_projectile_transform.translation.x,
-            alien_projectile_transform.translation.y,
-        );
-        let player_pos = Vec2::new(
-            player_transform.translation.x,
-            player_transform.translation.y,
-        );
-        if Vec2::distance(player_pos, alien_projectile_pos) < BULLET_RADIUS {
-            player.dead = true;
-            commands.entity(alien_projectile_entity).despawn();
-            events.write(PlayerKilledEvent {});
+    let mut hit_players = Vec::new();
+    for (alien_projectile_entity, alien_projectile_collider, alien_projectile_transform) in
+        alien_projectile_query.iter_mut()
+    {
+        for (player_entity, mut player, player_collider, player_transform) in
+            player_query.iter_mut()
+        {
+            if player.dead || untouchable_query.contains(player_entity) {
+                continue;
+            }
+            if alien_projectile_collider.collides(
+                alien_projectile_transform,
+                player_collider,
+                player_transform,
+            ) {
+                player.lives -= 1;
+                player.dead = player.lives <= 0;
+                pool.release(&mut commands, alien_projectile_entity);
+                hit_players.push(player_entity);
+                break;
+            }
+        }
+    }
+
+    if hit_players.is_empty() {
+        return;
+    }
+
+    //the run is over once every player is out of lives
+    if player_query.iter().all(|(_, player, ..)| player.dead) {
+        player_killed_events.write(PlayerKilledEvent {
+            cause: DeathCause::Shot,
+        });
+    } else {
+        for player in hit_players {
+            player_hit_events.write(PlayerHitEvent { player });
         }
     }
 }
 
 fn fire_projectile(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    textures: Res<Textures>,
+    mut pool: ResMut<Pool<AlienProjectile>>,
     mut cooldown_query: Query<&mut AlienProjectileCooldown>,
-    alien_query: Query<&mut Transform, (With<Alien>, Without<Dead>)>,
+    alien_query: Query<&mut Transform, (With<Alien>, Without<Dead>, Without<FlyingIn>)>,
     time: Res<Time>,
     resolution: Res<resolution::Resolution>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
     mut events: EventWriter<AlienShootEvent>,
 ) {
     let mut cooldown = cooldown_query.single_mut().unwrap();
-    let mut rng = rand::thread_rng();
     cooldown.shoot_timer -= time.delta_secs();
 
-    if cooldown.shoot_timer <= 0. {
-        if let Some(transform) = alien_query.iter().choose(&mut rng) {
-            events.write(AlienShootEvent {});
-            cooldown.shoot_timer = SHOOT_COOLDOWN;
-            let bullet_texture: Handle<Image> = asset_server.load("images/chain.png");
-            commands.spawn((
+    if cooldown.shoot_timer <= 0.
+        && let Some(transform) = alien_query.iter().choose(&mut rng.0)
+    {
+        events.write(AlienShootEvent {});
+        cooldown.shoot_timer = difficulty.alien_shoot_cooldown;
+        pool.spawn(
+            &mut commands,
+            (
                 Sprite {
-                    image: bullet_texture,
+                    image: textures.chain.clone(),
                     ..Default::default()
                 },
                 Transform::from_xyz(
@@ -118,9 +178,10 @@ fn fire_projectile(
                 )
                 .with_scale(Vec3::splat(resolution.pixel_ratio)),
                 AlienProjectile {
-                    speed: BULLET_SPEED,
+                    speed: difficulty.alien_bullet_speed,
                 },
-            ));
-        }
+                COLLIDER,
+            ),
+        );
     }
 }
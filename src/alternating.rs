@@ -0,0 +1,156 @@
+//! The arcade two-player mode where players take turns.
+//!
+//! Each player plays their own wave. When the player at the controls is hit, their wave is saved
+//! and the other player picks up their own wave where they left it.
+
+use bevy::prelude::*;
+
+use crate::alien::{Alien, AlienManager, Formation, RestoreWave, WaveSnapshot};
+use crate::alien_layouts::DEFAULT_MASK;
+use crate::alien_projectile::{PlayerHitEvent, PlayerKilledEvent};
+use crate::endless::EndlessMode;
+use crate::level_indicator::ScoreManager;
+use crate::localization::Localized;
+use crate::player::{AwaitingTurn, Player};
+use crate::pool::InPlay;
+use crate::settings::Settings;
+use crate::ufo::Ufo;
+use crate::widget;
+
+pub struct AlternatingPlugin;
+
+impl Plugin for AlternatingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Turns>().add_systems(
+            Update,
+            (reset_turns, switch_turns, destroy_turn_text).chain(),
+        );
+    }
+}
+
+//what a player left behind when their turn ended
+struct SavedTurn {
+    wave: WaveSnapshot,
+    level: i32,
+}
+
+#[derive(Resource, Default)]
+pub struct Turns {
+    saved: [Option<SavedTurn>; 2],
+}
+
+impl Turns {
+    //forget both players' waves, for when the run starts over
+    pub fn reset(&mut self) {
+        self.saved = Default::default();
+    }
+}
+
+#[derive(Component)]
+struct TurnText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.0;
+
+fn reset_turns(mut player_killed_events: EventReader<PlayerKilledEvent>, mut turns: ResMut<Turns>) {
+    if player_killed_events.read().count() > 0 {
+        turns.reset();
+    }
+}
+
+fn switch_turns(
+    mut commands: Commands,
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    settings: Res<Settings>,
+    mut turns: ResMut<Turns>,
+    mut player_query: Query<(Entity, &Player, &mut Visibility, Has<AwaitingTurn>)>,
+    alien_query: Query<(&Alien, &Transform)>,
+    mut alien_manager: ResMut<AlienManager>,
+    mut formation: ResMut<Formation>,
+    mut score_manager: ResMut<ScoreManager>,
+    mut endless: ResMut<EndlessMode>,
+    mut in_play: InPlay,
+    ufo_query: Query<Entity, With<Ufo>>,
+) {
+    if player_hit_events.read().count() == 0 || !settings.mode.takes_turns() {
+        return;
+    }
+
+    let Some((_, current, _, _)) = player_query.iter().find(|(.., waiting)| !waiting) else {
+        return;
+    };
+    let current = current.slot;
+    //a player whose opponent is out of lives just carries on
+    let Some((next_entity, next, _, _)) = player_query
+        .iter()
+        .find(|(_, player, _, waiting)| *waiting && !player.dead)
+    else {
+        return;
+    };
+    let next = next.slot;
+
+    turns.saved[current] = Some(SavedTurn {
+        wave: WaveSnapshot::new(&alien_manager, &formation, alien_query.iter()),
+        level: score_manager.curr_level,
+    });
+
+    match turns.saved[next].take() {
+        Some(saved) => {
+            score_manager.curr_level = saved.level;
+            endless.wave_level = saved.level;
+            commands.insert_resource(RestoreWave(saved.wave));
+        }
+        None => {
+            //the first turn of the second player starts from the first wave
+            score_manager.curr_level = 1;
+            alien_manager.reset = true;
+            endless.wave_level = 0;
+            if !settings.endless {
+                formation.mask = DEFAULT_MASK;
+            }
+        }
+    }
+
+    for (entity, player, mut visibility, _) in player_query.iter_mut() {
+        if entity == next_entity {
+            commands.entity(entity).remove::<AwaitingTurn>();
+            *visibility = Visibility::Inherited;
+        } else if player.slot == current {
+            commands.entity(entity).insert(AwaitingTurn);
+            *visibility = Visibility::Hidden;
+        }
+    }
+
+    //nothing from the last turn carries over into the next player's wave, not even a falling
+    //capsule or the saucer
+    in_play.clear(&mut commands);
+    for entity in ufo_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    commands.spawn((
+        widget::ui_center_root("Turn"),
+        GlobalZIndex(2),
+        TurnText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(
+            widget::large_text(""),
+            Localized::new("turn").arg("player", next + 1)
+        )],
+    ));
+}
+
+fn destroy_turn_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut TurnText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! A computer pilot that flies the ships in the attract mode demo.
+//!
+//! It drives each ship through [`PlayerInput`] just like the controls do: it steers clear of
+//! falling alien projectiles, lines up under the densest cluster of aliens to set off a chain
+//! reaction, and catches capsules when it is safe to.
+
+use bevy::prelude::*;
+
+use crate::alien::{Alien, AlienManager, Dead};
+use crate::alien_projectile::AlienProjectile;
+use crate::capsule::Capsule;
+use crate::difficulty::Difficulty;
+use crate::player::{AwaitingTurn, Player, PlayerInput, PlayerInputSystems};
+use crate::title_screen::Screen;
+
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            fly_ships
+                .in_set(PlayerInputSystems)
+                .run_if(in_state(Screen::Demo)),
+        );
+    }
+}
+
+//projectiles closer than this above the ship, and this close sideways, are dodged
+const DANGER_HEIGHT: f32 = 160.;
+const DANGER_WIDTH: f32 = 30.;
+//aliens this close together count as one cluster
+const CLUSTER_RADIUS: f32 = 45.;
+//how close to its mark the ship has to be before it moves or fires
+const AIM_TOLERANCE: f32 = 6.;
+
+fn fly_ships(
+    mut player_query: Query<(&Player, &Transform, &mut PlayerInput), Without<AwaitingTurn>>,
+    alien_query: Query<&Transform, (With<Alien>, Without<Dead>)>,
+    alien_projectile_query: Query<&Transform, With<AlienProjectile>>,
+    capsule_query: Query<&Transform, With<Capsule>>,
+    alien_manager: Res<AlienManager>,
+    difficulty: Res<Difficulty>,
+) {
+    let positions = |transform: &Transform| transform.translation.truncate();
+    let aliens: Vec<Vec2> = alien_query.iter().map(positions).collect();
+    let alien_projectiles: Vec<Vec2> = alien_projectile_query.iter().map(positions).collect();
+    let capsules: Vec<Vec2> = capsule_query.iter().map(positions).collect();
+
+    for (player, transform, mut input) in player_query.iter_mut() {
+        if player.dead {
+            continue;
+        }
+        *input = steer(
+            transform.translation.truncate(),
+            &aliens,
+            &alien_projectiles,
+            &capsules,
+            alien_manager.direction * alien_manager.speed,
+            difficulty.player_bullet_speed,
+        );
+    }
+}
+
+//what the autopilot would do with the ship at `ship`. the balancing simulator flies its scripted
+//bot with this too
+pub fn steer(
+    ship: Vec2,
+    aliens: &[Vec2],
+    alien_projectiles: &[Vec2],
+    capsules: &[Vec2],
+    alien_velocity: f32,
+    bullet_speed: f32,
+) -> PlayerInput {
+    if let Some(threat) = nearest_threat(ship, alien_projectiles) {
+        //step out from under the projectile, away from the side it is on
+        return PlayerInput {
+            horizontal: if threat.x > ship.x { -1. } else { 1. },
+            fire: false,
+        };
+    }
+
+    let capsule = capsules
+        .iter()
+        .filter(|capsule| capsule.y > ship.y)
+        .min_by(|a, b| a.y.total_cmp(&b.y));
+    let target = match capsule {
+        Some(capsule) => Some(capsule.x),
+        None => cluster_target(ship, aliens, alien_velocity, bullet_speed),
+    };
+
+    let Some(target) = target else {
+        return PlayerInput::default();
+    };
+    let offset = target - ship.x;
+    PlayerInput {
+        horizontal: if offset.abs() < AIM_TOLERANCE {
+            0.
+        } else {
+            offset.signum()
+        },
+        fire: capsule.is_none() && offset.abs() < AIM_TOLERANCE,
+    }
+}
+
+fn nearest_threat(ship: Vec2, alien_projectiles: &[Vec2]) -> Option<Vec2> {
+    alien_projectiles
+        .iter()
+        .filter(|projectile| {
+            let above = projectile.y - ship.y;
+            above > 0. && above < DANGER_HEIGHT && (projectile.x - ship.x).abs() < DANGER_WIDTH
+        })
+        .min_by(|a, b| a.y.total_cmp(&b.y))
+        .copied()
+}
+
+//the x to fire from to hit the alien with the most neighbours, leading it by the time our
+//projectile takes to climb up to it
+fn cluster_target(
+    ship: Vec2,
+    aliens: &[Vec2],
+    alien_velocity: f32,
+    bullet_speed: f32,
+) -> Option<f32> {
+    let target = aliens.iter().max_by_key(|alien| {
+        aliens
+            .iter()
+            .filter(|other| alien.distance(**other) < CLUSTER_RADIUS)
+            .count()
+    })?;
+
+    let flight_time = (target.y - ship.y) / bullet_speed;
+    Some(target.x + alien_velocity * flight_time)
+}
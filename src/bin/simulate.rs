@@ -0,0 +1,492 @@
+//! Plays many headless games with a bot and reports how they went, for balancing the game.
+//!
+//! ```text
+//! cargo run --release --bin simulate -- --games 1000 --levels 1,5,10 --policy scripted --format csv
+//! ```
+//!
+//! Every start level plays the same seeds, so a change to a constant such as the fire `COLLIDER` or to
+//! `levels/difficulty.curve.ron` can be judged by running again and comparing the output.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::ExitCode;
+
+use bevy::ecs::event::EventCursor;
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::Serialize;
+
+use chain_reaxian::alien::AlienManager;
+use chain_reaxian::alien_projectile::{DeathCause, PlayerKilledEvent};
+use chain_reaxian::autopilot;
+use chain_reaxian::capsule::CapsuleReleasedEvent;
+use chain_reaxian::difficulty::Difficulty;
+use chain_reaxian::endless::EndlessMode;
+use chain_reaxian::env::{Action, Environment, Observation, TIMESTEP};
+use chain_reaxian::level_indicator::ScoreManager;
+use chain_reaxian::player::Player;
+use chain_reaxian::projectile::AlienKilledEvent;
+use chain_reaxian::settings::Settings;
+
+const USAGE: &str = "\
+usage: simulate [options]
+  --games N          games to play from each start level (default 1000)
+  --levels 1,5,10    the levels the games start from (default 1)
+  --policy P         scripted or random (default scripted)
+  --seed N           the seed of the first game; game i plays seed N + i (default 0)
+  --max-seconds N    game time after which a game is stopped (default 600)
+  --endless          play endless mode instead of the classic wave
+  --format F         csv or json (default csv)
+  --out PATH         write to a file instead of stdout";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    //the autopilot from the attract mode demo
+    Scripted,
+    //holds a random move for a moment, firing at random
+    Random,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Options {
+    games: u64,
+    levels: Vec<i32>,
+    policy: Policy,
+    seed: u64,
+    max_seconds: f32,
+    endless: bool,
+    format: Format,
+    out: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            levels: vec![1],
+            policy: Policy::Scripted,
+            seed: 0,
+            max_seconds: 600.,
+            endless: false,
+            format: Format::Csv,
+            out: None,
+        }
+    }
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            if arg == "--endless" {
+                options.endless = true;
+                continue;
+            }
+            let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+            let invalid = || format!("invalid value for {arg}: {value}");
+            match arg.as_str() {
+                "--games" => options.games = value.parse().map_err(|_| invalid())?,
+                "--levels" => {
+                    options.levels = value
+                        .split(',')
+                        .map(|level| level.trim().parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid())?
+                }
+                "--policy" => {
+                    options.policy = match value.as_str() {
+                        "scripted" => Policy::Scripted,
+                        "random" => Policy::Random,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+                "--max-seconds" => options.max_seconds = value.parse().map_err(|_| invalid())?,
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "csv" => Format::Csv,
+                        "json" => Format::Json,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--out" => options.out = Some(value),
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+//how one game went
+enum Ending {
+    Died(DeathCause),
+    TimedOut,
+}
+
+struct GameStats {
+    //the start level plus the waves cleared. Catching a capsule also moves the level indicator on,
+    //so the game's own level would count capsules as waves
+    level_reached: i32,
+    seconds: f32,
+    //the game time it took to clear each wave that was cleared
+    clear_times: Vec<f32>,
+    kills: usize,
+    capsules: usize,
+    //the number of aliens killed by each chain reaction, counting a lone kill as a chain of one
+    chains: Vec<usize>,
+    ending: Ending,
+}
+
+fn play(env: &mut Environment, options: &Options, start_level: i32, seed: u64) -> GameStats {
+    let mut observation = env.reset(seed);
+    if start_level > 1 {
+        env.world_mut().resource_mut::<ScoreManager>().curr_level = start_level;
+        //the wave starts over at the start level, and endless mode generates its formation, as
+        //after the `level` command
+        env.world_mut().resource_mut::<AlienManager>().reset = true;
+        if let Some(mut endless) = env.world_mut().get_resource_mut::<EndlessMode>() {
+            endless.wave_level = 0;
+        }
+    }
+
+    let mut alien_killed = EventCursor::<AlienKilledEvent>::default();
+    let mut capsule_released = EventCursor::<CapsuleReleasedEvent>::default();
+    let mut player_killed = EventCursor::<PlayerKilledEvent>::default();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut random_action = Action::default();
+
+    let timestep = TIMESTEP.as_secs_f32();
+    let max_steps = (options.max_seconds / timestep) as u32;
+    let mut stats = GameStats {
+        level_reached: start_level,
+        seconds: 0.,
+        clear_times: Vec::new(),
+        kills: 0,
+        capsules: 0,
+        chains: Vec::new(),
+        ending: Ending::TimedOut,
+    };
+    let mut wave_started = 0.;
+    let mut chain = 0;
+
+    for step in 0..max_steps {
+        let action = match options.policy {
+            Policy::Scripted => scripted_action(env, &observation),
+            Policy::Random => {
+                if step % RANDOM_HOLD_STEPS == 0 {
+                    random_action = Action {
+                        horizontal: rng.gen_range(-1..=1) as f32,
+                        fire: rng.gen_bool(0.5),
+                    };
+                }
+                random_action
+            }
+        };
+        let had_aliens = !observation.aliens.is_empty();
+        let (next, _, done) = env.step(action);
+        observation = next;
+        stats.seconds = (step + 1) as f32 * timestep;
+
+        let world = env.world();
+        let kills = alien_killed
+            .read(world.resource::<Events<AlienKilledEvent>>())
+            .count();
+        stats.kills += kills;
+        stats.capsules += capsule_released
+            .read(world.resource::<Events<CapsuleReleasedEvent>>())
+            .count();
+        let death = player_killed
+            .read(world.resource::<Events<PlayerKilledEvent>>())
+            .next()
+            .map(|event| event.cause);
+
+        //a chain goes on for as long as the fires it started keep burning
+        chain += kills;
+        if chain > 0 && kills == 0 && observation.fires.is_empty() {
+            stats.chains.push(chain);
+            chain = 0;
+        }
+
+        let has_aliens = !observation.aliens.is_empty();
+        if had_aliens && !has_aliens && !done {
+            stats.clear_times.push(stats.seconds - wave_started);
+        } else if !had_aliens && has_aliens {
+            wave_started = stats.seconds;
+        }
+
+        if let Some(cause) = death {
+            stats.ending = Ending::Died(cause);
+            break;
+        }
+    }
+
+    if chain > 0 {
+        stats.chains.push(chain);
+    }
+    stats.level_reached = start_level + stats.clear_times.len() as i32;
+    stats
+}
+
+//how many steps the random policy keeps doing the same thing
+const RANDOM_HOLD_STEPS: u32 = 15;
+
+fn scripted_action(env: &mut Environment, observation: &Observation) -> Action {
+    let world = env.world_mut();
+    let Some(ship) = world
+        .query::<(&Player, &Transform)>()
+        .iter(world)
+        .find(|(player, _)| player.slot == 0)
+        .map(|(_, transform)| transform.translation.truncate())
+    else {
+        return Action::default();
+    };
+    let alien_manager = world.resource::<AlienManager>();
+    let difficulty = world.resource::<Difficulty>();
+
+    let aliens: Vec<Vec2> = observation
+        .aliens
+        .iter()
+        .map(|alien| alien.position)
+        .collect();
+    let input = autopilot::steer(
+        ship,
+        &aliens,
+        &observation.alien_projectiles,
+        &observation.capsules,
+        alien_manager.direction * alien_manager.speed,
+        difficulty.player_bullet_speed,
+    );
+    Action {
+        horizontal: input.horizontal,
+        fire: input.fire,
+    }
+}
+
+//the games from one start level, summed up
+#[derive(Serialize)]
+struct Summary {
+    start_level: i32,
+    games: usize,
+    avg_level_reached: f32,
+    avg_seconds_played: f32,
+    avg_seconds_to_clear: f32,
+    waves_cleared: usize,
+    kills: usize,
+    //capsules released per alien killed
+    capsule_drop_rate: f32,
+    avg_chain_size: f32,
+    max_chain_size: usize,
+    //how many chains killed each number of aliens
+    chain_sizes: BTreeMap<usize, usize>,
+    deaths_shot: usize,
+    deaths_landed: usize,
+    timeouts: usize,
+}
+
+//0 when there is nothing to average
+fn mean(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0., 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { 0. } else { sum / count as f32 }
+}
+
+impl Summary {
+    fn new(start_level: i32, games: &[GameStats]) -> Self {
+        let clear_times: Vec<f32> = games
+            .iter()
+            .flat_map(|game| game.clear_times.iter().copied())
+            .collect();
+        let kills = games.iter().map(|game| game.kills).sum();
+        let chains: Vec<usize> = games
+            .iter()
+            .flat_map(|game| game.chains.iter().copied())
+            .collect();
+        let mut chain_sizes = BTreeMap::new();
+        for chain in &chains {
+            *chain_sizes.entry(*chain).or_default() += 1;
+        }
+        let endings = |matches: fn(&Ending) -> bool| {
+            games.iter().filter(|game| matches(&game.ending)).count()
+        };
+
+        Self {
+            start_level,
+            games: games.len(),
+            avg_level_reached: mean(games.iter().map(|game| game.level_reached as f32)),
+            avg_seconds_played: mean(games.iter().map(|game| game.seconds)),
+            avg_seconds_to_clear: mean(clear_times.iter().copied()),
+            waves_cleared: clear_times.len(),
+            kills,
+            capsule_drop_rate: games.iter().map(|game| game.capsules).sum::<usize>() as f32
+                / kills.max(1) as f32,
+            avg_chain_size: mean(chains.iter().map(|chain| *chain as f32)),
+            max_chain_size: chains.iter().copied().max().unwrap_or_default(),
+            chain_sizes,
+            deaths_shot: endings(|ending| matches!(ending, Ending::Died(DeathCause::Shot))),
+            deaths_landed: endings(|ending| matches!(ending, Ending::Died(DeathCause::Landed))),
+            timeouts: endings(|ending| matches!(ending, Ending::TimedOut)),
+        }
+    }
+
+    //chain sizes are bucketed so every row has the same columns
+    const CSV_HEADER: &str = "start_level,games,avg_level_reached,avg_seconds_played,\
+avg_seconds_to_clear,waves_cleared,kills,capsule_drop_rate,avg_chain_size,max_chain_size,\
+chains_1,chains_2,chains_3_5,chains_6_10,chains_11_plus,deaths_shot,deaths_landed,timeouts";
+
+    fn csv_row(&self) -> String {
+        let chains = |sizes: std::ops::RangeInclusive<usize>| {
+            self.chain_sizes
+                .range(sizes)
+                .map(|(_, count)| count)
+                .sum::<usize>()
+        };
+        format!(
+            "{},{},{:.2},{:.1},{:.1},{},{},{:.4},{:.2},{},{},{},{},{},{},{},{},{}",
+            self.start_level,
+            self.games,
+            self.avg_level_reached,
+            self.avg_seconds_played,
+            self.avg_seconds_to_clear,
+            self.waves_cleared,
+            self.kills,
+            self.capsule_drop_rate,
+            self.avg_chain_size,
+            self.max_chain_size,
+            chains(1..=1),
+            chains(2..=2),
+            chains(3..=5),
+            chains(6..=10),
+            chains(11..=usize::MAX),
+            self.deaths_shot,
+            self.deaths_landed,
+            self.timeouts,
+        )
+    }
+}
+
+//games are shared out between this many threads, each with its own game
+fn thread_count() -> u64 {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get() as u64)
+}
+
+fn simulate(options: &Options, start_level: i32) -> Vec<GameStats> {
+    let threads = thread_count().min(options.games.max(1));
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread| {
+                scope.spawn(move || {
+                    let mut env = Environment::new(Settings {
+                        endless: options.endless,
+                        ..default()
+                    });
+                    (thread..options.games)
+                        .step_by(threads as usize)
+                        .map(|game| {
+                            let seed = options.seed + game;
+                            (game, play(&mut env, options, start_level, seed))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut games: Vec<(u64, GameStats)> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        games.sort_by_key(|(game, _)| *game);
+        games.into_iter().map(|(_, stats)| stats).collect()
+    })
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let summaries: Vec<Summary> = options
+        .levels
+        .iter()
+        .map(|start_level| {
+            eprintln!(
+                "playing {} games from level {start_level}...",
+                options.games
+            );
+            Summary::new(*start_level, &simulate(&options, *start_level))
+        })
+        .collect();
+
+    let output = match options.format {
+        Format::Csv => std::iter::once(String::from(Summary::CSV_HEADER))
+            .chain(summaries.iter().map(Summary::csv_row))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Json => serde_json::to_string_pretty(&summaries).unwrap(),
+    };
+
+    let result = match &options.out {
+        Some(path) => std::fs::write(path, output + "\n"),
+        None => writeln!(std::io::stdout(), "{output}"),
+    };
+    if let Err(err) = result {
+        eprintln!("could not write the results: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(options: &Options, start_level: i32) -> Vec<GameStats> {
+        let mut env = Environment::new(Settings::default());
+        (0..options.games)
+            .map(|game| play(&mut env, options, start_level, options.seed + game))
+            .collect()
+    }
+
+    #[test]
+    fn levels_reached_count_cleared_waves() {
+        let options = Options {
+            games: 2,
+            max_seconds: 60.,
+            ..default()
+        };
+        for start_level in [1, 5] {
+            let games = games(&options, start_level);
+            for game in &games {
+                assert_eq!(
+                    game.level_reached,
+                    start_level + game.clear_times.len() as i32
+                );
+            }
+            let summary = Summary::new(start_level, &games);
+            assert_eq!(summary.games, 2);
+            assert_eq!(
+                summary.avg_level_reached,
+                start_level as f32 + summary.waves_cleared as f32 / 2.
+            );
+        }
+    }
+
+    #[test]
+    fn same_seeds_same_summary() {
+        let options = Options {
+            games: 2,
+            seed: 7,
+            max_seconds: 30.,
+            ..default()
+        };
+        let first = Summary::new(1, &games(&options, 1)).csv_row();
+        assert_eq!(first, Summary::new(1, &games(&options, 1)).csv_row());
+    }
+}
@@ -0,0 +1,328 @@
+//! Destructible bunkers between the player and the formation, described per level in
+//! `levels/bunkers.bunkers.ron`.
+//!
+//! A bunker is a mask of pixels kept on the CPU alongside the image drawn for it. Chains and alien
+//! projectiles blow a small crater where they hit and are used up, fire burns away whatever it
+//! touches, and aliens marching through wear away the pixels they cover. The bunkers for the
+//! current level are rebuilt whenever a wave starts.
+
+use std::collections::BTreeMap;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use serde::Deserialize;
+
+use crate::alien::{Dead, WaveStartedEvent};
+use crate::alien_projectile::AlienProjectile;
+use crate::collider::{Collider, Layers, Shape};
+use crate::fly_in::FlyingIn;
+use crate::level_indicator::ScoreManager;
+use crate::level_table::{LevelKeyed, LevelTable};
+use crate::pool::Pool;
+use crate::preload::Preload;
+use crate::projectile::Projectile;
+use crate::resolution;
+use crate::ron_asset::RonAssetPlugin;
+
+pub struct BunkerPlugin;
+
+impl Plugin for BunkerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<BunkerLayouts>::new(&["bunkers.ron"]))
+            .init_resource::<RebuildBunkers>()
+            .add_systems(Startup, setup_bunker_layouts)
+            .add_systems(Update, erode_bunkers)
+            //once the level has gone up for the wave that is starting
+            .add_systems(PostUpdate, (queue_rebuild, build_bunkers).chain());
+    }
+}
+
+const LAYOUTS_PATH: &str = "levels/bunkers.bunkers.ron";
+//how much bigger a bunker pixel is than a sprite pixel
+const PIXEL_SCALE: f32 = 2.;
+const HEIGHT: f32 = -190.;
+//the share of the screen's width the bunkers are spread across
+const SPREAD: f32 = 0.8;
+//the radius, in bunker pixels, of the crater a projectile leaves
+const CRATER_RADIUS: f32 = 2.5;
+const COLOR: Color = Color::srgb(0.3, 0.9, 0.4);
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct BunkerLayouts {
+    //rows of `#` and `.`, top row first
+    pub shapes: BTreeMap<String, Vec<String>>,
+    pub levels: LevelTable<BunkerLevel>,
+}
+
+#[derive(Deserialize)]
+pub struct BunkerLevel {
+    //the first level this applies to, until the next one
+    pub level: i32,
+    pub count: usize,
+    pub shape: String,
+}
+
+impl LevelKeyed for BunkerLevel {
+    fn level(&self) -> i32 {
+        self.level
+    }
+}
+
+#[derive(Component)]
+pub struct Bunker {
+    width: usize,
+    height: usize,
+    //row by row, top row first
+    solid: Vec<bool>,
+}
+
+impl Bunker {
+    fn new(shape: &[String]) -> Self {
+        let width = shape
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut solid = vec![false; width * shape.len()];
+        for (row, line) in shape.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                solid[row * width + col] = c == '#';
+            }
+        }
+        Self {
+            width,
+            height: shape.len(),
+            solid,
+        }
+    }
+
+    fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    fn image(&self) -> Image {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: self.width as u32,
+                height: self.height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            //kept on the CPU so the damage can be drawn into it
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        );
+        image.sampler = ImageSampler::nearest();
+        for (index, solid) in self.solid.iter().enumerate() {
+            if *solid {
+                let (x, y) = ((index % self.width) as u32, (index / self.width) as u32);
+                let _ = image.set_color_at(x, y, Color::WHITE);
+            }
+        }
+        image
+    }
+
+    //the pixels whose centres are inside `shape`, `offset` from the bunker's centre in its own
+    //unscaled pixels
+    fn pixels_in(&self, shape: Shape, offset: Vec2) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let half_size = shape.half_size();
+        let half_bunker = self.size() * 0.5;
+        //pixel columns go right and rows go down
+        let first = (Vec2::new(offset.x - half_size.x, -offset.y - half_size.y) + half_bunker)
+            .floor()
+            .max(Vec2::ZERO);
+        let last = (Vec2::new(offset.x + half_size.x, -offset.y + half_size.y) + half_bunker)
+            .ceil()
+            .min(self.size());
+
+        (first.y as usize..last.y as usize)
+            .flat_map(move |row| (first.x as usize..last.x as usize).map(move |col| (row, col)))
+            .filter(move |(row, col)| {
+                let center = Vec2::new(
+                    *col as f32 + 0.5 - half_bunker.x,
+                    half_bunker.y - (*row as f32 + 0.5),
+                );
+                shape.contains(center - offset)
+            })
+    }
+
+    fn solid_in(&self, shape: Shape, offset: Vec2) -> bool {
+        self.pixels_in(shape, offset)
+            .any(|(row, col)| self.solid[row * self.width + col])
+    }
+
+    //clears the pixels inside `shape`, returning which of them were solid
+    fn clear(&mut self, shape: Shape, offset: Vec2) -> Vec<(usize, usize)> {
+        let cleared: Vec<(usize, usize)> = self
+            .pixels_in(shape, offset)
+            .filter(|(row, col)| self.solid[row * self.width + col])
+            .collect();
+        for (row, col) in &cleared {
+            self.solid[row * self.width + col] = false;
+        }
+        cleared
+    }
+
+    fn destroyed(&self) -> bool {
+        !self.solid.contains(&true)
+    }
+}
+
+#[derive(Resource)]
+struct BunkerLayoutsHandle(Handle<BunkerLayouts>);
+
+//set when the bunkers need building for a new wave, including the first
+#[derive(Resource)]
+struct RebuildBunkers(bool);
+
+impl Default for RebuildBunkers {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn setup_bunker_layouts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preload: ResMut<Preload>,
+) {
+    commands.insert_resource(BunkerLayoutsHandle(
+        preload.load(&asset_server, LAYOUTS_PATH),
+    ));
+}
+
+fn queue_rebuild(
+    mut wave_started_events: EventReader<WaveStartedEvent>,
+    mut rebuild: ResMut<RebuildBunkers>,
+) {
+    if wave_started_events.read().count() > 0 {
+        rebuild.0 = true;
+    }
+}
+
+fn build_bunkers(
+    mut commands: Commands,
+    mut rebuild: ResMut<RebuildBunkers>,
+    layouts_handle: Res<BunkerLayoutsHandle>,
+    layouts: Res<Assets<BunkerLayouts>>,
+    score_manager: Res<ScoreManager>,
+    resolution: Res<resolution::Resolution>,
+    bunker_query: Query<Entity, With<Bunker>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !rebuild.0 {
+        return;
+    }
+    let Some(layouts) = layouts.get(&layouts_handle.0) else {
+        return;
+    };
+    rebuild.0 = false;
+
+    for entity in bunker_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let level = layouts.levels.at(score_manager.curr_level);
+    let Some(shape) = layouts.shapes.get(&level.shape) else {
+        warn!("there is no bunker shape called {}", level.shape);
+        return;
+    };
+
+    let spread = resolution.screen_dimensions.x * SPREAD;
+    for index in 0..level.count {
+        let bunker = Bunker::new(shape);
+        let x = ((index as f32 + 0.5) / level.count as f32 - 0.5) * spread;
+        commands.spawn((
+            Name::new("Bunker"),
+            Sprite {
+                image: images.add(bunker.image()),
+                color: COLOR,
+                ..default()
+            },
+            Transform::from_xyz(x, HEIGHT, 4.)
+                .with_scale(Vec3::splat(resolution.pixel_ratio * PIXEL_SCALE)),
+            Collider::rect(bunker.size().x, bunker.size().y).on(Layers::BUNKER),
+            bunker,
+        ));
+    }
+}
+
+fn erode_bunkers(
+    mut commands: Commands,
+    mut bunker_query: Query<(Entity, &mut Bunker, &Collider, &Transform, &Sprite)>,
+    hitter_query: Query<
+        (
+            Entity,
+            &Collider,
+            &Transform,
+            Has<Projectile>,
+            Has<AlienProjectile>,
+        ),
+        (Without<Bunker>, Without<Dead>, Without<FlyingIn>),
+    >,
+    mut projectile_pool: ResMut<Pool<Projectile>>,
+    mut alien_projectile_pool: ResMut<Pool<AlienProjectile>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut used_up = Vec::new();
+    for (bunker_entity, mut bunker, bunker_collider, bunker_transform, sprite) in
+        bunker_query.iter_mut()
+    {
+        let scale = bunker_transform.scale.truncate();
+        let mut cleared = Vec::new();
+        for (entity, collider, transform, projectile, alien_projectile) in hitter_query.iter() {
+            if used_up.contains(&entity)
+                || !collider.collides(transform, bunker_collider, bunker_transform)
+            {
+                continue;
+            }
+            //in the bunker's own pixels
+            let offset = (transform.translation - bunker_transform.translation).truncate() / scale;
+            let shape = collider.world_shape(transform).scaled(scale.recip());
+
+            if projectile || alien_projectile {
+                if bunker.solid_in(shape, offset) {
+                    let crater = Shape::Circle {
+                        radius: shape.half_size().max_element() + CRATER_RADIUS,
+                    };
+                    cleared.extend(bunker.clear(crater, offset));
+                    used_up.push(entity);
+                    if projectile {
+                        projectile_pool.release(&mut commands, entity);
+                    } else {
+                        alien_projectile_pool.release(&mut commands, entity);
+                    }
+                }
+            } else {
+                cleared.extend(bunker.clear(shape, offset));
+            }
+        }
+
+        if bunker.destroyed() {
+            commands.entity(bunker_entity).despawn();
+        } else if !cleared.is_empty()
+            && let Some(image) = images.get_mut(&sprite.image)
+        {
+            for (row, col) in cleared {
+                let _ = image.set_color_at(col as u32, row as u32, Color::NONE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_layouts_have_their_shapes() {
+        let layouts: BunkerLayouts =
+            ron::de::from_str(include_str!("../assets/levels/bunkers.bunkers.ron")).unwrap();
+        for level in 1..=30 {
+            let shape = &layouts.levels.at(level).shape;
+            assert!(layouts.shapes.contains_key(shape), "{shape}");
+        }
+    }
+}
@@ -1,83 +1,193 @@
 use bevy::prelude::*;
 use rand::Rng;
 
-use crate::player;
+use crate::collider::{Collider, Layers};
+use crate::console::ConsoleAppExt;
+use crate::difficulty::Difficulty;
+use crate::player::{self, Player};
+use crate::pool::{Pool, PoolAppExt, Textures};
 use crate::projectile::AlienKilledEvent;
 use crate::resolution;
+use crate::rng::GameRng;
+use crate::weapon;
 
 pub struct CapsulePlugin;
 
-const CAPSULE_PCT: f32 = 4.0;
-const CAPSULE_RADIUS: f32 = 24.;
+pub const CAPSULE_PCT: f32 = 4.0;
+//the capsule is caught anywhere on its sprite, which is a bit wider than the capsule itself
+pub const COLLIDER: Collider = Collider::sprite().on(Layers::CAPSULE).hits(Layers::PLAYER);
 
 impl Plugin for CapsulePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_capsule_counter).add_systems(
             Update,
-            (spawn_capsules, update_capsules, update_capsule_interactions),
+            (
+                spawn_capsules,
+                spawn_requested_capsules,
+                update_capsules,
+                update_capsule_interactions,
+            ),
         );
         app.add_event::<CapsuleCollisionEvent>();
         app.add_event::<CapsuleReleasedEvent>();
+        app.add_event::<SpawnCapsuleEvent>();
+        app.add_console_command("spawn capsule", spawn_capsule_command);
+        app.add_pool::<Capsule>(POOL_SIZE);
     }
 }
 
+//a capsule from the UFO or the console can fall alongside the one from an alien
+const POOL_SIZE: usize = 4;
+
+//capsules released by the cheat fall from this height
+const CHEAT_CAPSULE_HEIGHT: f32 = 100.;
+
+//a cheat for the console and the debug panel: releases a capsule above the first player
+pub fn release_above_player(world: &mut World) -> Result<(), String> {
+    let x = world
+        .query::<(&Player, &Transform)>()
+        .iter(world)
+        .find(|(player, _)| player.slot == 0)
+        .map(|(_, transform)| transform.translation.x)
+        .ok_or("there is no player")?;
+    world.send_event(SpawnCapsuleEvent {
+        location: Vec2::new(x, CHEAT_CAPSULE_HEIGHT),
+    });
+    Ok(())
+}
+
+fn spawn_capsule_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    if args != ["capsule"] {
+        return Err(String::from("usage: spawn capsule"));
+    }
+    release_above_player(world)?;
+    Ok(String::from("capsule released"))
+}
+
 #[derive(Component)]
-struct CapsuleCounter {
-    num_capsules: i32,
+pub struct CapsuleCounter {
+    pub num_capsules: i32,
 }
 
 #[derive(Component)]
 pub struct Capsule {
     pub speed: f32,
+    //the weapon it gives whoever catches it, see `weapon::by_name`
+    pub weapon: Option<&'static str>,
 }
 
 #[derive(Event, Debug)]
 pub struct CapsuleReleasedEvent {}
 
+//releases a capsule at `location` no matter how many are already falling, e.g. for a cheat
+#[derive(Event, Debug)]
+pub struct SpawnCapsuleEvent {
+    pub location: Vec2,
+}
+
 fn setup_capsule_counter(mut commands: Commands) {
     commands.spawn(CapsuleCounter { num_capsules: 0 });
 }
 
 // The maximum number of capsules on the screen at one time.
 const MAX_CAPSULES: i32 = 1;
+//the chance out of 100 that a capsule from an alien carries a weapon other than the chain gun
+const WEAPON_PCT: f32 = 25.0;
 
 fn spawn_capsules(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    textures: Res<Textures>,
+    mut pool: ResMut<Pool<Capsule>>,
     mut alien_killed_events: EventReader<AlienKilledEvent>,
     mut events: EventWriter<CapsuleReleasedEvent>,
     resolution: Res<resolution::Resolution>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
     mut counter_query: Query<&mut CapsuleCounter>,
 ) {
-    let mut rng = rand::thread_rng();
     for event in alien_killed_events.read() {
         let mut counter = counter_query.single_mut().unwrap();
         if counter.num_capsules >= MAX_CAPSULES {
             return;
         }
 
-        let pct = rng.gen_range(0.0..100.0);
-        if pct < CAPSULE_PCT {
-            let capsule_image = asset_server.load("images/orange_capsule.png");
+        let pct = rng.0.gen_range(0.0..100.0);
+        if pct < difficulty.capsule_pct {
             counter.num_capsules += 1;
             events.write(CapsuleReleasedEvent {});
-            commands.spawn((
-                Sprite {
-                    image: capsule_image,
-                    ..Default::default()
-                },
-                Transform::from_xyz(event.location.x, event.location.y, 5.0)
-                    .with_scale(Vec3::splat(resolution.pixel_ratio)),
-                Capsule { speed: 120.0 },
-            ));
+            let weapon = (rng.0.gen_range(0.0..100.0) < WEAPON_PCT)
+                .then(|| weapon::NAMES[rng.0.gen_range(1..weapon::NAMES.len())]);
+            release_capsule(
+                &mut commands,
+                &mut pool,
+                &textures,
+                &resolution,
+                event.location,
+                weapon,
+            );
         }
     }
 }
 
+fn spawn_requested_capsules(
+    mut commands: Commands,
+    textures: Res<Textures>,
+    mut pool: ResMut<Pool<Capsule>>,
+    mut spawn_capsule_events: EventReader<SpawnCapsuleEvent>,
+    mut events: EventWriter<CapsuleReleasedEvent>,
+    resolution: Res<resolution::Resolution>,
+    mut counter_query: Query<&mut CapsuleCounter>,
+) {
+    for event in spawn_capsule_events.read() {
+        counter_query.single_mut().unwrap().num_capsules += 1;
+        events.write(CapsuleReleasedEvent {});
+        release_capsule(
+            &mut commands,
+            &mut pool,
+            &textures,
+            &resolution,
+            event.location,
+            None,
+        );
+    }
+}
+
+fn release_capsule(
+    commands: &mut Commands,
+    pool: &mut Pool<Capsule>,
+    textures: &Textures,
+    resolution: &resolution::Resolution,
+    location: Vec2,
+    weapon: Option<&'static str>,
+) {
+    //tinted the colour of the weapon it carries
+    let color = weapon
+        .and_then(weapon::by_name)
+        .map_or(Color::WHITE, |weapon| weapon.color());
+    pool.spawn(
+        commands,
+        (
+            Sprite {
+                image: textures.capsule.clone(),
+                color,
+                ..Default::default()
+            },
+            Transform::from_xyz(location.x, location.y, 5.0)
+                .with_scale(Vec3::splat(resolution.pixel_ratio)),
+            Capsule {
+                speed: 120.0,
+                weapon,
+            },
+            COLLIDER,
+        ),
+    );
+}
+
 //move the capsule
 fn update_capsules(
     mut commands: Commands,
     mut capsule_query: Query<(Entity, &Capsule, &mut Transform)>,
+    mut pool: ResMut<Pool<Capsule>>,
     time: Res<Time>,
     resolution: Res<resolution::Resolution>,
     mut counter_query: Query<&mut CapsuleCounter>,
@@ -85,7 +195,7 @@ fn update_capsules(
     for (entity, capsule, mut transform) in capsule_query.iter_mut() {
         transform.translation.y -= capsule.speed * time.delta_secs();
         if transform.translation.y.abs() > resolution.screen_dimensions.y * 0.5 {
-            commands.entity(entity).despawn();
+            pool.release(&mut commands, entity);
             let mut counter = counter_query.single_mut().unwrap();
             counter.num_capsules -= 1;
         }
@@ -93,29 +203,36 @@ fn update_capsules(
 }
 
 #[derive(Event, Debug)]
-pub struct CapsuleCollisionEvent {}
+pub struct CapsuleCollisionEvent {
+    //the player who caught the capsule
+    pub player: Entity,
+    pub weapon: Option<&'static str>,
+}
 
 fn update_capsule_interactions(
-    mut player_query: Query<(&mut player::Player, &Transform)>,
-    mut capsule_query: Query<(Entity, &Transform), With<Capsule>>,
+    mut player_query: Query<
+        (Entity, &player::Player, &Collider, &Transform),
+        Without<player::AwaitingTurn>,
+    >,
+    mut capsule_query: Query<(Entity, &Capsule, &Collider, &Transform)>,
     mut commands: Commands,
+    mut pool: ResMut<Pool<Capsule>>,
     mut events: EventWriter<CapsuleCollisionEvent>,
     mut counter_query: Query<&mut CapsuleCounter>,
 ) {
-    for (_, player_transform) in player_query.iter_mut() {
-        for (capsule_entity, capsule_transform) in capsule_query.iter_mut() {
-            let capsule_pos = Vec2::new(
-                capsule_transform.translation.x,
-                capsule_transform.translation.y,
-            );
-            let player_pos = Vec2::new(
-                player_transform.translation.x,
-                player_transform.translation.y,
-            );
-            if Vec2::distance(player_pos, capsule_pos) < CAPSULE_RADIUS {
-                //best to not despawn in the query but the warning doesn't break the game so I don't mind too much
-                commands.entity(capsule_entity).despawn();
-                events.write(CapsuleCollisionEvent {});
+    for (player_entity, player, player_collider, player_transform) in player_query.iter_mut() {
+        if player.dead {
+            continue;
+        }
+        for (capsule_entity, capsule, capsule_collider, capsule_transform) in
+            capsule_query.iter_mut()
+        {
+            if capsule_collider.collides(capsule_transform, player_collider, player_transform) {
+                pool.release(&mut commands, capsule_entity);
+                events.write(CapsuleCollisionEvent {
+                    player: player_entity,
+                    weapon: capsule.weapon,
+                });
                 let mut counter = counter_query.single_mut().unwrap();
                 counter.num_capsules -= 1;
             }
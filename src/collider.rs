@@ -0,0 +1,272 @@
+//! Shapes for everything that collides, and the layers that say what collides with what.
+//!
+//! A [`Collider`] is sized in the sprite's own pixels and scaled by the entity's [`Transform`], so
+//! it follows `pixel_ratio` like the sprite does. Two colliders touch if they overlap and either
+//! one's mask includes the other's layers. A collider made with [`Collider::sprite`] is fitted to
+//! the sprite once its image has loaded, and touches nothing until then.
+
+use std::ops::BitOr;
+
+use bevy::prelude::*;
+
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, fit_sprite_colliders);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Layers(u32);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const PLAYER: Layers = Layers(1);
+    pub const PLAYER_SHOT: Layers = Layers(1 << 1);
+    pub const ALIEN: Layers = Layers(1 << 2);
+    pub const ALIEN_SHOT: Layers = Layers(1 << 3);
+    pub const FIRE: Layers = Layers(1 << 4);
+    pub const CAPSULE: Layers = Layers(1 << 5);
+    pub const BUNKER: Layers = Layers(1 << 6);
+    pub const UFO: Layers = Layers(1 << 7);
+
+    pub const fn union(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+
+    pub const fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        self.union(other)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+    Circle { radius: f32 },
+    //axis aligned, so rotation is ignored
+    Rect { half_size: Vec2 },
+    //waiting to be replaced by a rectangle the size of the sprite
+    Sprite,
+}
+
+impl Shape {
+    //half the size of the box around the shape
+    pub fn half_size(self) -> Vec2 {
+        match self {
+            Shape::Circle { radius } => Vec2::splat(radius),
+            Shape::Rect { half_size } => half_size,
+            Shape::Sprite => Vec2::ZERO,
+        }
+    }
+
+    //whether a point `offset` from the centre is inside the shape
+    pub fn contains(self, offset: Vec2) -> bool {
+        match self {
+            Shape::Circle { radius } => offset.length_squared() < radius * radius,
+            Shape::Rect { half_size } => {
+                offset.x.abs() < half_size.x && offset.y.abs() < half_size.y
+            }
+            Shape::Sprite => false,
+        }
+    }
+
+    pub fn scaled(self, scale: Vec2) -> Shape {
+        let scale = scale.abs();
+        match self {
+            Shape::Circle { radius } => Shape::Circle {
+                radius: radius * scale.max_element(),
+            },
+            Shape::Rect { half_size } => Shape::Rect {
+                half_size: half_size * scale,
+            },
+            Shape::Sprite => Shape::Sprite,
+        }
+    }
+
+    //whether this shape overlaps `other` when it is `offset` away
+    fn overlaps(self, other: Shape, offset: Vec2) -> bool {
+        match (self, other) {
+            (Shape::Circle { radius: a }, Shape::Circle { radius: b }) => {
+                offset.length_squared() < (a + b) * (a + b)
+            }
+            (Shape::Rect { half_size: a }, Shape::Rect { half_size: b }) => {
+                let reach = a + b;
+                offset.x.abs() < reach.x && offset.y.abs() < reach.y
+            }
+            (Shape::Circle { radius }, Shape::Rect { half_size }) => {
+                //from the closest point of the rectangle to the circle's centre
+                let closest = (-offset).clamp(-half_size, half_size);
+                (-offset - closest).length_squared() < radius * radius
+            }
+            (Shape::Rect { .. }, Shape::Circle { .. }) => other.overlaps(self, -offset),
+            (Shape::Sprite, _) | (_, Shape::Sprite) => false,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct Collider {
+    pub shape: Shape,
+    //what this is
+    pub layers: Layers,
+    //what it collides with
+    pub mask: Layers,
+}
+
+impl Collider {
+    pub const fn circle(radius: f32) -> Self {
+        Self::new(Shape::Circle { radius })
+    }
+
+    pub const fn rect(width: f32, height: f32) -> Self {
+        Self::new(Shape::Rect {
+            half_size: Vec2::new(width * 0.5, height * 0.5),
+        })
+    }
+
+    pub const fn sprite() -> Self {
+        Self::new(Shape::Sprite)
+    }
+
+    const fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            layers: Layers::NONE,
+            mask: Layers::NONE,
+        }
+    }
+
+    pub const fn on(self, layers: Layers) -> Self {
+        Self { layers, ..self }
+    }
+
+    pub const fn hits(self, mask: Layers) -> Self {
+        Self { mask, ..self }
+    }
+
+    //the shape as it is in the world
+    pub fn world_shape(&self, transform: &Transform) -> Shape {
+        self.shape.scaled(transform.scale.truncate())
+    }
+
+    pub fn collides(
+        &self,
+        transform: &Transform,
+        other: &Collider,
+        other_transform: &Transform,
+    ) -> bool {
+        (self.mask.intersects(other.layers) || other.mask.intersects(self.layers))
+            && self.world_shape(transform).overlaps(
+                other.world_shape(other_transform),
+                (other_transform.translation - transform.translation).truncate(),
+            )
+    }
+}
+
+fn fit_sprite_colliders(
+    mut collider_query: Query<(&mut Collider, &Sprite)>,
+    images: Res<Assets<Image>>,
+    layouts: Option<Res<Assets<TextureAtlasLayout>>>,
+) {
+    for (mut collider, sprite) in collider_query.iter_mut() {
+        if collider.shape != Shape::Sprite {
+            continue;
+        }
+        //a sprite from an atlas is the size of its frame rather than the whole image
+        let size = sprite.custom_size.or_else(|| match &sprite.texture_atlas {
+            Some(atlas) => layouts
+                .as_ref()
+                .and_then(|layouts| atlas.texture_rect(layouts))
+                .map(|rect| rect.size().as_vec2()),
+            None => images.get(&sprite.image).map(Image::size_f32),
+        });
+        if let Some(size) = size {
+            collider.shape = Shape::Rect {
+                half_size: size * 0.5,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIRCLE: Shape = Shape::Circle { radius: 2. };
+    const RECT: Shape = Shape::Rect {
+        half_size: Vec2::new(3., 1.),
+    };
+
+    #[test]
+    fn points_on_the_edge_are_outside() {
+        assert!(CIRCLE.contains(Vec2::ZERO));
+        assert!(CIRCLE.contains(Vec2::new(1.9, 0.)));
+        assert!(!CIRCLE.contains(Vec2::new(0., 2.)));
+        assert!(RECT.contains(Vec2::new(-2.9, 0.9)));
+        assert!(!RECT.contains(Vec2::new(3., 0.)));
+        assert!(!RECT.contains(Vec2::new(0., -1.)));
+        assert!(!Shape::Sprite.contains(Vec2::ZERO));
+    }
+
+    #[test]
+    fn shapes_that_only_touch_do_not_overlap() {
+        assert!(!CIRCLE.overlaps(CIRCLE, Vec2::new(4., 0.)));
+        assert!(CIRCLE.overlaps(CIRCLE, Vec2::new(3.9, 0.)));
+        assert!(!RECT.overlaps(RECT, Vec2::new(6., 0.)));
+        assert!(!RECT.overlaps(RECT, Vec2::new(0., 2.)));
+        assert!(RECT.overlaps(RECT, Vec2::new(5.9, 1.9)));
+        //the circle sits on the rectangle's right edge, then just past its corner
+        assert!(!RECT.overlaps(CIRCLE, Vec2::new(5., 0.)));
+        assert!(CIRCLE.overlaps(RECT, Vec2::new(-4.9, 0.)));
+        assert!(!RECT.overlaps(CIRCLE, Vec2::new(4.5, 2.5)));
+        assert!(RECT.overlaps(CIRCLE, Vec2::new(4., 2.)));
+    }
+
+    #[test]
+    fn shapes_inside_one_another_overlap() {
+        let small = Shape::Circle { radius: 0.5 };
+        let big = Shape::Rect {
+            half_size: Vec2::splat(10.),
+        };
+        assert!(small.overlaps(big, Vec2::new(3., -4.)));
+        assert!(big.overlaps(small, Vec2::new(3., -4.)));
+        assert!(small.overlaps(CIRCLE, Vec2::ZERO));
+        assert!(!Shape::Sprite.overlaps(big, Vec2::ZERO));
+        assert!(!big.overlaps(Shape::Sprite, Vec2::ZERO));
+    }
+
+    #[test]
+    fn layers_pick_what_collides() {
+        let at = |x| Transform::from_xyz(x, 0., 0.);
+        let shot = Collider::circle(1.)
+            .on(Layers::PLAYER_SHOT)
+            .hits(Layers::ALIEN);
+        let alien = Collider::circle(1.).on(Layers::ALIEN);
+        let player = Collider::circle(1.).on(Layers::PLAYER);
+        //either side's mask is enough
+        assert!(shot.collides(&at(0.), &alien, &at(1.)));
+        assert!(alien.collides(&at(1.), &shot, &at(0.)));
+        assert!(!shot.collides(&at(0.), &player, &at(1.)));
+        assert!(!alien.collides(&at(0.), &player, &at(1.)));
+        //and the shapes still have to meet
+        assert!(!shot.collides(&at(0.), &alien, &at(5.)));
+    }
+
+    #[test]
+    fn colliders_scale_with_the_transform() {
+        let alien = Collider::rect(2., 2.).on(Layers::ALIEN);
+        let shot = Collider::circle(1.).hits(Layers::ALIEN);
+        let small = Transform::from_xyz(3., 0., 0.);
+        let big = small.with_scale(Vec3::splat(3.));
+        assert!(!shot.collides(&Transform::default(), &alien, &small));
+        assert!(shot.collides(&Transform::default(), &alien, &big));
+    }
+}
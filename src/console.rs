@@ -0,0 +1,298 @@
+//! The developer console, opened with the backtick key in dev builds.
+//!
+//! Plugins add their own commands with [`ConsoleAppExt::add_console_command`], and `help` lists
+//! them all. Tab completes a command, up and down go through the history, and everything the
+//! console prints is logged as well. The game doesn't see any keys while the console is open.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use bevy::input::InputSystem;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::widget;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_console_command("help", show_help)
+            .add_console_command("clear", clear_console)
+            .add_systems(Startup, setup_console)
+            .add_systems(PreUpdate, read_console_input.after(InputSystem))
+            .add_systems(
+                Update,
+                (
+                    run_console_commands,
+                    update_console_text.run_if(resource_changed::<Console>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
+const MAX_LINES: usize = 12;
+
+//runs a command with the words typed after its name, returning what to print
+pub type ConsoleCommandFn = fn(&mut World, &[&str]) -> Result<String, String>;
+
+pub struct ConsoleCommand {
+    //the name followed by its arguments, e.g. `wave load <path>`
+    pub usage: &'static str,
+    pub run: ConsoleCommandFn,
+}
+
+impl ConsoleCommand {
+    fn name(&self) -> &'static str {
+        self.usage.split_whitespace().next().unwrap_or_default()
+    }
+
+    //what tab completes to: the usage up to the first argument to fill in
+    fn completion(&self) -> &'static str {
+        self.usage.split('<').next().unwrap_or_default().trim_end()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(Vec<ConsoleCommand>);
+
+pub trait ConsoleAppExt {
+    //makes the command in `usage` available in the console. commands can be added whether or not
+    //the console is, so plugins don't need to know about the `dev` feature
+    fn add_console_command(&mut self, usage: &'static str, run: ConsoleCommandFn) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn add_console_command(&mut self, usage: &'static str, run: ConsoleCommandFn) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<ConsoleCommands>()
+            .0
+            .push(ConsoleCommand { usage, run });
+        self
+    }
+}
+
+//parses the argument at `index`, for commands to report a missing or mistyped argument
+pub fn arg<T: FromStr>(args: &[&str], index: usize, name: &str) -> Result<T, String> {
+    let value = args.get(index).ok_or_else(|| format!("missing {name}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("{value} is not a valid {name}"))
+}
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    lines: VecDeque<String>,
+    //what has been entered, oldest first, and where in it the up and down keys have got to
+    history: Vec<String>,
+    browsing: Option<usize>,
+    //entered lines waiting to be run
+    pending: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        info!("console: {line}");
+        self.lines.push_back(line);
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    fn enter(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        self.browsing = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(format!("> {line}"));
+        self.history.push(line.clone());
+        self.pending.push(line);
+    }
+
+    fn browse(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let index = match (self.browsing, older) {
+            (None, true) => self.history.len() - 1,
+            (None, false) => return,
+            (Some(index), true) => index.saturating_sub(1),
+            (Some(index), false) if index + 1 < self.history.len() => index + 1,
+            (Some(_), false) => {
+                self.browsing = None;
+                self.input.clear();
+                return;
+            }
+        };
+        self.browsing = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    //completes the input as far as every matching command agrees, listing them if there are several
+    fn complete(&mut self, commands: &ConsoleCommands) {
+        let matches: Vec<&str> = commands
+            .0
+            .iter()
+            .map(ConsoleCommand::completion)
+            .filter(|completion| completion.starts_with(self.input.as_str()))
+            .collect();
+        let Some(first) = matches.first() else {
+            return;
+        };
+
+        let common = matches.iter().fold(*first, |common, completion| {
+            let length = common
+                .char_indices()
+                .zip(completion.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((index, a), _)| index + a.len_utf8());
+            &common[..length]
+        });
+        if matches.len() == 1 {
+            self.input = format!("{common} ");
+        } else {
+            self.input = common.to_string();
+            let options = matches.join(", ");
+            self.print(options);
+        }
+    }
+}
+
+#[derive(Component)]
+struct ConsoleRoot;
+
+#[derive(Component)]
+struct ConsoleOutputText;
+
+#[derive(Component)]
+struct ConsoleInputText;
+
+fn setup_console(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Console"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Px(10.0)),
+            row_gap: Px(6.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0., 0., 0., 0.85)),
+        GlobalZIndex(10),
+        Visibility::Hidden,
+        ConsoleRoot,
+        children![
+            (widget::small_text(""), ConsoleOutputText),
+            (widget::small_text("> "), ConsoleInputText),
+        ],
+    ));
+}
+
+fn read_console_input(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut console: ResMut<Console>,
+    commands: Res<ConsoleCommands>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        if event.key_code == TOGGLE_KEY {
+            console.open = !console.open;
+            continue;
+        }
+        if !console.open {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => console.enter(),
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Tab => console.complete(&commands),
+            Key::ArrowUp => console.browse(true),
+            Key::ArrowDown => console.browse(false),
+            Key::Escape => console.open = false,
+            _ => {
+                if let Some(text) = &event.text {
+                    console
+                        .input
+                        .extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+
+    //the game only gets the keys while the console is closed, and never the toggle
+    if console.open || keys.just_pressed(TOGGLE_KEY) {
+        keys.reset_all();
+    }
+}
+
+fn run_console_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    for line in pending {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let run = world
+            .resource::<ConsoleCommands>()
+            .0
+            .iter()
+            .find(|command| command.name() == words[0])
+            .map(|command| command.run);
+
+        let output = match run {
+            Some(run) => run(world, &words[1..]).unwrap_or_else(|err| format!("error: {err}")),
+            None => format!("unknown command {}, try help", words[0]),
+        };
+        if !output.is_empty() {
+            world.resource_mut::<Console>().print(output);
+        }
+    }
+}
+
+fn update_console_text(
+    console: Res<Console>,
+    mut root_query: Query<&mut Visibility, With<ConsoleRoot>>,
+    mut output_query: Query<&mut Text, (With<ConsoleOutputText>, Without<ConsoleInputText>)>,
+    mut input_query: Query<&mut Text, With<ConsoleInputText>>,
+) {
+    for mut visibility in root_query.iter_mut() {
+        *visibility = if console.open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    for mut text in output_query.iter_mut() {
+        text.0 = console.lines.iter().cloned().collect::<Vec<_>>().join("\n");
+    }
+    for mut text in input_query.iter_mut() {
+        text.0 = format!("> {}_", console.input);
+    }
+}
+
+fn show_help(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let usages: Vec<&str> = world
+        .resource::<ConsoleCommands>()
+        .0
+        .iter()
+        .map(|command| command.usage)
+        .collect();
+    Ok(usages.join("\n"))
+}
+
+fn clear_console(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    world.resource_mut::<Console>().lines.clear();
+    Ok(String::new())
+}
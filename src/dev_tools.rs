@@ -0,0 +1,150 @@
+//! Development tools, only built with the `dev` feature.
+//!
+//! Press F1 to show the world inspector, a debug panel with entity counts and cheats, and the
+//! outline of every collider.
+
+use bevy::input::common_conditions::input_toggle_active;
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContextPass, EguiContexts, EguiPlugin};
+use bevy_inspector_egui::egui;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+use crate::alien::{Alien, Dead};
+use crate::capsule::{self, Capsule};
+use crate::collider::{Collider, Layers, Shape};
+use crate::fire::Fire;
+use crate::player::{self, Invincible, MAX_GUN_LEVEL, Player};
+use crate::projectile::AlienKilledEvent;
+
+pub struct DevToolsPlugin;
+
+impl Plugin for DevToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            EguiPlugin {
+                enable_multipass_for_primary_context: true,
+            },
+            WorldInspectorPlugin::new().run_if(input_toggle_active(false, TOGGLE_KEY)),
+        ))
+        .add_systems(
+            EguiContextPass,
+            debug_panel.run_if(input_toggle_active(false, TOGGLE_KEY)),
+        )
+        .add_systems(
+            Update,
+            draw_collision_gizmos.run_if(input_toggle_active(false, TOGGLE_KEY)),
+        );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+
+fn debug_panel(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Player, Has<Invincible>)>,
+    mut alien_query: Query<(&mut Alien, &Transform), Without<Dead>>,
+    fire_query: Query<(), With<Fire>>,
+    capsule_query: Query<(), With<Capsule>>,
+    mut alien_killed_events: EventWriter<AlienKilledEvent>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+    let aliens = alien_query.iter().filter(|(alien, _)| !alien.dead).count();
+    let first_player = player_query
+        .iter()
+        .find(|(_, player, ..)| player.slot == 0)
+        .map(|(entity, player, invincible)| (entity, player.gun_level(), invincible));
+
+    egui::Window::new("Debug").show(ctx, |ui| {
+        ui.label(format!("Aliens: {aliens}"));
+        ui.label(format!("Fires: {}", fire_query.iter().count()));
+        ui.label(format!("Capsules: {}", capsule_query.iter().count()));
+        ui.separator();
+
+        let Some((first_entity, gun_level, invincible)) = first_player else {
+            return;
+        };
+
+        let mut invincible = invincible;
+        if ui.checkbox(&mut invincible, "Invincible").changed() {
+            commands.queue(move |world: &mut World| player::set_invincible(world, invincible));
+        }
+
+        let mut gun_level = gun_level;
+        if ui
+            .add(egui::Slider::new(&mut gun_level, 0..=MAX_GUN_LEVEL).text("Gun level"))
+            .changed()
+        {
+            for (_, mut player, ..) in player_query.iter_mut() {
+                player.set_gun_level(gun_level);
+            }
+        }
+
+        if ui.button("Spawn capsule").clicked() {
+            commands.queue(|world: &mut World| {
+                if let Err(err) = capsule::release_above_player(world) {
+                    warn!("{err}");
+                }
+            });
+        }
+
+        //killing every alien scores them and sets them alight, while skipping the level just
+        //clears them away
+        if ui.button("Kill all").clicked() {
+            for (mut alien, transform) in alien_query.iter_mut() {
+                if alien.dead {
+                    continue;
+                }
+                alien.dead = true;
+                alien_killed_events.write(AlienKilledEvent {
+                    alien_type: alien.alien_type,
+                    location: transform.translation.truncate(),
+                    player: first_entity,
+                });
+            }
+        }
+        if ui.button("Skip level").clicked() {
+            for (mut alien, _) in alien_query.iter_mut() {
+                alien.dead = true;
+            }
+        }
+    });
+}
+
+fn draw_collision_gizmos(
+    mut gizmos: Gizmos,
+    collider_query: Query<(&Collider, &Transform, &InheritedVisibility)>,
+) {
+    for (collider, transform, visibility) in collider_query.iter() {
+        if !visibility.get() {
+            continue;
+        }
+        let isometry = Isometry2d::from_translation(transform.translation.truncate());
+        let color = layer_color(collider.layers);
+        match collider.world_shape(transform) {
+            Shape::Circle { radius } => {
+                gizmos.circle_2d(isometry, radius, color);
+            }
+            Shape::Rect { half_size } => {
+                gizmos.rect_2d(isometry, half_size * 2., color);
+            }
+            Shape::Sprite => {}
+        }
+    }
+}
+
+fn layer_color(layers: Layers) -> Color {
+    if layers.intersects(Layers::PLAYER_SHOT) {
+        Color::WHITE
+    } else if layers.intersects(Layers::ALIEN_SHOT) {
+        Color::srgb(1., 0.3, 0.3)
+    } else if layers.intersects(Layers::FIRE) {
+        Color::srgb(1., 0.6, 0.)
+    } else if layers.intersects(Layers::CAPSULE) {
+        Color::srgb(0.3, 1., 0.3)
+    } else {
+        Color::srgb(0.4, 0.6, 1.)
+    }
+}
@@ -0,0 +1,217 @@
+//! Per-level difficulty, read from the curve in `levels/difficulty.curve.ron`.
+//!
+//! The curve lists the difficulty at a few levels and the levels in between are interpolated. The
+//! [`Difficulty`] resource holds the values for the level each wave started at, so going up a level
+//! mid-wave only makes the game harder from the next wave.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::alien::{self, RestoreWave, WaveStartedEvent};
+use crate::alien_projectile;
+use crate::capsule;
+use crate::level_indicator::ScoreManager;
+use crate::level_table::LevelTable;
+use crate::player;
+use crate::preload::Preload;
+use crate::ron_asset::RonAssetPlugin;
+use crate::ufo;
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<DifficultyCurve>::new(&["curve.ron"]))
+            .insert_resource(Difficulty::default())
+            .init_resource::<ApplyCurve>()
+            .add_systems(Startup, setup_difficulty_curve)
+            //once the level has gone up for the wave that is starting
+            .add_systems(PostUpdate, (queue_difficulty, update_difficulty).chain());
+    }
+}
+
+const CURVE_PATH: &str = "levels/difficulty.curve.ron";
+
+#[derive(Resource, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Difficulty {
+    //speed of the formation at the start of a wave, and how much it speeds up as aliens die
+    pub initial_alien_speed: f32,
+    pub alien_speed_increment: f32,
+    pub alien_shoot_cooldown: f32,
+    pub alien_bullet_speed: f32,
+    pub player_shoot_cooldown: f32,
+    pub player_bullet_speed: f32,
+    //the chance out of 100 that a killed alien drops a capsule
+    pub capsule_pct: f32,
+    //the mean number of seconds between mystery UFOs
+    pub ufo_interval: f32,
+}
+
+//the difficulty the game was tuned with, used until the curve has loaded
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            initial_alien_speed: alien::INITIAL_ALIEN_SPEED,
+            alien_speed_increment: alien::ALIEN_SPEED_INCREMENT,
+            alien_shoot_cooldown: alien_projectile::SHOOT_COOLDOWN,
+            alien_bullet_speed: alien_projectile::BULLET_SPEED,
+            player_shoot_cooldown: player::SHOOT_COOLDOWN,
+            player_bullet_speed: player::BULLET_SPEED,
+            capsule_pct: capsule::CAPSULE_PCT,
+            ufo_interval: ufo::INTERVAL,
+        }
+    }
+}
+
+impl Difficulty {
+    fn lerp(&self, other: &Difficulty, t: f32) -> Difficulty {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Difficulty {
+            initial_alien_speed: mix(self.initial_alien_speed, other.initial_alien_speed),
+            alien_speed_increment: mix(self.alien_speed_increment, other.alien_speed_increment),
+            alien_shoot_cooldown: mix(self.alien_shoot_cooldown, other.alien_shoot_cooldown),
+            alien_bullet_speed: mix(self.alien_bullet_speed, other.alien_bullet_speed),
+            player_shoot_cooldown: mix(self.player_shoot_cooldown, other.player_shoot_cooldown),
+            player_bullet_speed: mix(self.player_bullet_speed, other.player_bullet_speed),
+            capsule_pct: mix(self.capsule_pct, other.capsule_pct),
+            ufo_interval: mix(self.ufo_interval, other.ufo_interval),
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct DifficultyCurve {
+    //(level, difficulty) pairs in increasing level order
+    pub points: LevelTable<(i32, Difficulty)>,
+}
+
+impl DifficultyCurve {
+    //levels before the first point or after the last one use the nearest point
+    pub fn at_level(&self, level: i32) -> Difficulty {
+        let ((_, from), (_, to), t) = self.points.around(level);
+        from.lerp(to, t)
+    }
+}
+
+#[derive(Resource)]
+struct DifficultyCurveHandle(Handle<DifficultyCurve>);
+
+//set when the curve needs applying for a new wave, including the first
+#[derive(Resource)]
+struct ApplyCurve(bool);
+
+impl Default for ApplyCurve {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn setup_difficulty_curve(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preload: ResMut<Preload>,
+) {
+    commands.insert_resource(DifficultyCurveHandle(
+        preload.load(&asset_server, CURVE_PATH),
+    ));
+}
+
+//queues the curve for the wave that is starting, or the saved wave that resumes when players take
+//turns, so the level going up mid-wave, e.g. for a capsule, leaves the difficulty as it is
+fn queue_difficulty(
+    mut wave_started_events: EventReader<WaveStartedEvent>,
+    mut curve_events: EventReader<AssetEvent<DifficultyCurve>>,
+    restore: Option<Res<RestoreWave>>,
+    mut apply: ResMut<ApplyCurve>,
+) {
+    //a curve that is edited while the game runs applies at once
+    let edited = curve_events.read().count() > 0;
+    if wave_started_events.read().count() > 0 || edited || restore.is_some() {
+        apply.0 = true;
+    }
+}
+
+fn update_difficulty(
+    mut apply: ResMut<ApplyCurve>,
+    curve_handle: Res<DifficultyCurveHandle>,
+    curves: Res<Assets<DifficultyCurve>>,
+    score_manager: Res<ScoreManager>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    if !apply.0 {
+        return;
+    }
+    let Some(curve) = curves.get(&curve_handle.0) else {
+        return;
+    };
+    apply.0 = false;
+
+    difficulty.set_if_neq(curve.at_level(score_manager.curr_level));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> DifficultyCurve {
+        let easy = Difficulty::default();
+        let hard = Difficulty {
+            initial_alien_speed: easy.initial_alien_speed + 40.,
+            capsule_pct: easy.capsule_pct * 2.,
+            ..easy
+        };
+        DifficultyCurve {
+            points: LevelTable::try_from(vec![(2, easy), (6, hard)]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn levels_between_points_are_interpolated() {
+        let easy = Difficulty::default();
+        let difficulty = curve().at_level(3);
+        assert_eq!(
+            difficulty.initial_alien_speed,
+            easy.initial_alien_speed + 10.
+        );
+        assert_eq!(difficulty.capsule_pct, easy.capsule_pct * 1.25);
+        assert_eq!(difficulty.alien_bullet_speed, easy.alien_bullet_speed);
+    }
+
+    #[test]
+    fn levels_outside_the_curve_use_the_nearest_point() {
+        let curve = curve();
+        assert_eq!(curve.at_level(1), curve.at_level(2));
+        assert_eq!(curve.at_level(-5), Difficulty::default());
+        assert_eq!(curve.at_level(6), curve.at_level(100));
+        assert_eq!(
+            curve.at_level(100).initial_alien_speed,
+            Difficulty::default().initial_alien_speed + 40.
+        );
+    }
+
+    #[test]
+    fn shipped_curve_loads() {
+        let curve: DifficultyCurve =
+            ron::de::from_str(include_str!("../assets/levels/difficulty.curve.ron")).unwrap();
+        assert!(curve.at_level(1).initial_alien_speed > 0.);
+    }
+
+    #[test]
+    fn unordered_curves_are_rejected() {
+        let point = |level| {
+            format!(
+                "({level}, (initial_alien_speed: 35.0, alien_speed_increment: 12.0, \
+alien_shoot_cooldown: 1.2, alien_bullet_speed: 240.0, player_shoot_cooldown: 0.9, \
+player_bullet_speed: 400.0, capsule_pct: 4.0, ufo_interval: 25.0))"
+            )
+        };
+        let curve = |levels: &[i32]| {
+            let points: Vec<String> = levels.iter().map(|level| point(*level)).collect();
+            ron::de::from_str::<DifficultyCurve>(&format!("(points: [{}])", points.join(",")))
+        };
+        assert!(curve(&[1, 5]).is_ok());
+        assert!(curve(&[]).is_err());
+        assert!(curve(&[5, 1]).is_err());
+        assert!(curve(&[1, 1]).is_err());
+    }
+}
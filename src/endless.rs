@@ -0,0 +1,145 @@
+//! Endless mode, where every level brings a new formation from the wave generator instead of the
+//! default wave.
+//!
+//! Press E to switch between endless mode and the default wave. The choice is saved in
+//! [`Settings`], along with an optional seed to replay the same run.
+
+use bevy::prelude::*;
+
+use crate::alien::{self, AlienManager, Formation, WaveStartedEvent};
+use crate::alien_layouts::DEFAULT_MASK;
+use crate::console::{self, ConsoleAppExt};
+use crate::level_indicator::{self, ScoreManager};
+use crate::localization::Localized;
+use crate::rng::GameRng;
+use crate::settings::Settings;
+use crate::wave_generator;
+use crate::widget;
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_endless)
+            .add_systems(
+                Update,
+                (
+                    toggle_endless,
+                    //once a cleared wave has raised the level, and before the formation is spawned
+                    next_wave
+                        .after(alien::manage_alien_logic)
+                        .after(level_indicator::update_level_complete)
+                        .before(alien::spawn_formation),
+                    destroy_endless_text,
+                )
+                    .chain(),
+            )
+            .add_console_command("seed <n>", set_seed);
+    }
+}
+
+#[derive(Resource)]
+pub struct EndlessMode {
+    pub seed: u64,
+    //the level the current formation was generated for, 0 before the first one
+    pub wave_level: i32,
+}
+
+#[derive(Component)]
+struct EndlessText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.5;
+
+fn setup_endless(mut commands: Commands, settings: Res<Settings>) {
+    commands.insert_resource(EndlessMode {
+        seed: settings.endless_seed.unwrap_or_else(rand::random),
+        wave_level: 0,
+    });
+}
+
+//reseeds the endless waves and everything else random, starting the wave over
+fn set_seed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let seed: u64 = console::arg(args, 0, "seed")?;
+    let mut endless = world.resource_mut::<EndlessMode>();
+    endless.seed = seed;
+    endless.wave_level = 0;
+    *world.resource_mut::<GameRng>() = GameRng::seeded(seed);
+    world.resource_mut::<AlienManager>().reset = true;
+    Ok(format!("seed {seed}"))
+}
+
+fn next_wave(
+    settings: Res<Settings>,
+    score_manager: Res<ScoreManager>,
+    mut endless: ResMut<EndlessMode>,
+    mut formation: ResMut<Formation>,
+    mut wave_started_events: EventReader<WaveStartedEvent>,
+) {
+    let wave_started = wave_started_events.read().count() > 0;
+    if !settings.endless {
+        return;
+    }
+
+    //collecting a capsule also raises the level, but the formation only changes when a cleared
+    //wave starts the next one, or the level drops as the player dies and starts over
+    let next_level = wave_started && score_manager.curr_level > endless.wave_level;
+    let started_over = score_manager.curr_level < endless.wave_level;
+    if endless.wave_level != 0 && !next_level && !started_over {
+        return;
+    }
+
+    endless.wave_level = score_manager.curr_level.max(1);
+    formation.mask = wave_generator::generate(endless.seed, endless.wave_level);
+}
+
+fn toggle_endless(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut endless: ResMut<EndlessMode>,
+    mut formation: ResMut<Formation>,
+    mut score_manager: ResMut<ScoreManager>,
+    mut alien_manager: ResMut<AlienManager>,
+) {
+    if !keys.just_pressed(KeyCode::KeyE) {
+        return;
+    }
+
+    //switching mode starts the run over from the first level
+    settings.endless = !settings.endless;
+    score_manager.curr_level = 1;
+    alien_manager.reset = true;
+    endless.wave_level = 0;
+    if !settings.endless {
+        formation.mask = DEFAULT_MASK;
+    }
+
+    let text = if settings.endless {
+        Localized::new("endless_mode").arg("seed", endless.seed)
+    } else {
+        Localized::new("classic_mode")
+    };
+    commands.spawn((
+        widget::ui_center_root("Endless Mode"),
+        GlobalZIndex(2),
+        EndlessText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(widget::large_text(""), text)],
+    ));
+}
+
+fn destroy_endless_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut EndlessText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
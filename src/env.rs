@@ -0,0 +1,289 @@
+//! A headless version of the game for training agents against.
+//!
+//! [`Environment`] runs the [`GameplayPlugin`] without a window or audio and advances a fixed
+//! [`TIMESTEP`] of game time on every [`Environment::step`], as fast as the machine allows. The
+//! agent flies the first player's ship. The seed passed to [`Environment::reset`] fixes every random
+//! choice the game makes, so taking the same actions after the same seed replays the same episode.
+
+use std::time::Duration;
+
+use bevy::asset::AssetMetaCheck;
+use bevy::ecs::event::EventCursor;
+use bevy::ecs::query::QueryFilter;
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::image::{CompressedImageFormats, ImageLoader};
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::alien::{Alien, AlienType};
+use crate::alien_projectile::{AlienProjectile, PlayerHitEvent, PlayerKilledEvent};
+use crate::capsule::Capsule;
+use crate::fire::Fire;
+use crate::game::GameplayPlugin;
+use crate::level_indicator::ScoreManager;
+use crate::player::{Player, PlayerInput};
+use crate::preload;
+use crate::projectile::{AlienKilledEvent, Projectile};
+use crate::resolution::{Resolution, WINDOW_SIZE};
+use crate::rng::GameRng;
+use crate::settings::Settings;
+use crate::ufo::UfoKilledEvent;
+
+//the game time that passes on every step
+pub const TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+//taken off the reward whenever the agent loses a life; killing an alien or the UFO is worth its
+//points
+pub const DEATH_PENALTY: f32 = 100.;
+
+//what the agent does this step, the same as a player pressing the controls
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Action {
+    //-1 for full speed left up to 1 for full speed right
+    pub horizontal: f32,
+    pub fire: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AlienObservation {
+    pub position: Vec2,
+    pub alien_type: AlienType,
+}
+
+//positions are in world units with the origin in the middle of the screen, see `WINDOW_SIZE`
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Observation {
+    pub player_x: f32,
+    pub level: i32,
+    //only the aliens still alive
+    pub aliens: Vec<AlienObservation>,
+    //the players' projectiles, heading up
+    pub projectiles: Vec<Vec2>,
+    pub alien_projectiles: Vec<Vec2>,
+    pub fires: Vec<Vec2>,
+    pub capsules: Vec<Vec2>,
+}
+
+pub struct Environment {
+    settings: Settings,
+    app: App,
+    alien_killed: EventCursor<AlienKilledEvent>,
+    ufo_killed: EventCursor<UfoKilledEvent>,
+    player_hit: EventCursor<PlayerHitEvent>,
+    player_killed: EventCursor<PlayerKilledEvent>,
+}
+
+impl Environment {
+    //`settings` picks the mode to play, e.g. endless mode; they are never saved
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            app: build_app(&settings, 0),
+            settings,
+            alien_killed: default(),
+            ufo_killed: default(),
+            player_hit: default(),
+            player_killed: default(),
+        }
+    }
+
+    //starts a new episode from the first level
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.app = build_app(&self.settings, seed);
+        self.alien_killed = default();
+        self.ufo_killed = default();
+        self.player_hit = default();
+        self.player_killed = default();
+        self.observe()
+    }
+
+    //plays one `TIMESTEP` and returns what the agent sees after it, the reward for it and whether
+    //the run is over. The game starts over by itself after that, but the episode should be `reset`
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        let agent = self.agent();
+        if let Some(mut input) = agent.and_then(|agent| self.app.world_mut().get_mut(agent)) {
+            *input = PlayerInput {
+                horizontal: action.horizontal,
+                fire: action.fire,
+            };
+        }
+
+        self.app.update();
+
+        let world = self.app.world();
+        let mut reward = 0.;
+        for event in self
+            .alien_killed
+            .read(world.resource::<Events<AlienKilledEvent>>())
+        {
+            if Some(event.player) == agent {
+                reward += event.alien_type.points() as f32;
+            }
+        }
+        for event in self
+            .ufo_killed
+            .read(world.resource::<Events<UfoKilledEvent>>())
+        {
+            if Some(event.player) == agent {
+                reward += event.points as f32;
+            }
+        }
+        for event in self
+            .player_hit
+            .read(world.resource::<Events<PlayerHitEvent>>())
+        {
+            if Some(event.player) == agent {
+                reward -= DEATH_PENALTY;
+            }
+        }
+        let done = self
+            .player_killed
+            .read(world.resource::<Events<PlayerKilledEvent>>())
+            .count()
+            > 0;
+        if done {
+            reward -= DEATH_PENALTY;
+        }
+
+        (self.observe(), reward, done)
+    }
+
+    //the game itself, for looking at anything the observation leaves out
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    fn agent(&mut self) -> Option<Entity> {
+        let world = self.app.world_mut();
+        world
+            .query::<(Entity, &Player)>()
+            .iter(world)
+            .find(|(_, player)| player.slot == 0)
+            .map(|(entity, _)| entity)
+    }
+
+    fn observe(&mut self) -> Observation {
+        let world = self.app.world_mut();
+        let player_x = world
+            .query::<(&Player, &Transform)>()
+            .iter(world)
+            .find(|(player, _)| player.slot == 0)
+            .map(|(_, transform)| transform.translation.x)
+            .unwrap_or_default();
+        let aliens = world
+            .query::<(&Alien, &Transform)>()
+            .iter(world)
+            .filter(|(alien, _)| !alien.dead)
+            .map(|(alien, transform)| AlienObservation {
+                position: transform.translation.truncate(),
+                alien_type: alien.alien_type,
+            })
+            .collect();
+
+        Observation {
+            player_x,
+            level: world.resource::<ScoreManager>().curr_level,
+            aliens,
+            projectiles: positions::<With<Projectile>>(world),
+            alien_projectiles: positions::<With<AlienProjectile>>(world),
+            fires: positions::<With<Fire>>(world),
+            capsules: positions::<With<Capsule>>(world),
+        }
+    }
+}
+
+fn positions<F: QueryFilter>(world: &mut World) -> Vec<Vec2> {
+    world
+        .query_filtered::<&Transform, F>()
+        .iter(world)
+        .map(|transform| transform.translation.truncate())
+        .collect()
+}
+
+fn build_app(settings: &Settings, seed: u64) -> App {
+    let mut app = App::new();
+    app.insert_resource(Settings {
+        endless_seed: settings.endless_seed.or(Some(seed)),
+        ..settings.clone()
+    })
+    .insert_resource(GameRng::seeded(seed))
+    .insert_resource(Resolution::new(WINDOW_SIZE))
+    .add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            meta_check: AssetMetaCheck::Never,
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+        InputPlugin,
+    ))
+    //the sprites are never drawn, but they are loaded so colliders can be fitted to them
+    .init_asset::<Image>()
+    .register_asset_loader(ImageLoader::new(CompressedImageFormats::NONE))
+    .add_plugins(GameplayPlugin)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+
+    //on one thread the game makes its random choices in the same order every time
+    app.edit_schedule(Update, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+    app.finish();
+    app.cleanup();
+
+    //no game time passes while the assets the game plays by load, starting with the update that
+    //sets out to load them
+    app.update();
+    while preload::loading(app.world()) {
+        app.update();
+    }
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(TIMESTEP));
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //enough game time for aliens to shoot and capsules to drop
+    const STEPS: u32 = 1200;
+
+    //sweeps from side to side firing every other step, so nothing but the seed changes the episode
+    fn play(env: &mut Environment, seed: u64) -> Vec<Observation> {
+        let mut observations = vec![env.reset(seed)];
+        for step in 0..STEPS {
+            let action = Action {
+                horizontal: ((step / 60) % 3) as f32 - 1.,
+                fire: step % 2 == 0,
+            };
+            observations.push(env.step(action).0);
+        }
+        observations
+    }
+
+    #[test]
+    fn same_seed_same_episode() {
+        let mut env = Environment::new(Settings::default());
+        let first = play(&mut env, 7);
+        //the seed has had choices to make, such as which aliens shoot
+        assert!(
+            first
+                .iter()
+                .any(|observation| !observation.alien_projectiles.is_empty())
+        );
+        assert_eq!(first, play(&mut env, 7));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        let mut env = Environment::new(Settings::default());
+        let first = play(&mut env, 7);
+        let second = play(&mut env, 8);
+        //both start from the same wave, and only the random choices tell them apart
+        assert_eq!(first[0], second[0]);
+        assert_ne!(first, second);
+    }
+}
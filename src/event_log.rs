@@ -0,0 +1,400 @@
+//! Records gameplay events to a JSON Lines file for analysing difficulty offline.
+//!
+//! Press L to start or stop logging. The choice is saved in [`Settings`]. Every session gets its
+//! own file in the `logs` folder of the data directory, with one event per line stamped with the
+//! game time and level. The autopilot's games in the attract mode demo are left out. The file is
+//! flushed every second, when logging stops and on exit.
+
+use std::io::{BufWriter, Write};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::alien::{AlienType, SpeedChangedEvent};
+use crate::alien_projectile::{DeathCause, PlayerHitEvent, PlayerKilledEvent};
+use crate::capsule::{CapsuleCollisionEvent, CapsuleReleasedEvent};
+use crate::level_indicator::{LevelCompletedEvent, ScoreManager};
+use crate::localization::Localized;
+use crate::player::Player;
+use crate::projectile::AlienKilledEvent;
+use crate::settings::Settings;
+use crate::storage;
+use crate::title_screen::Screen;
+use crate::ufo::UfoKilledEvent;
+use crate::widget;
+
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>()
+            .add_systems(
+                Update,
+                (
+                    toggle_event_log,
+                    (
+                        log_events::<AlienKilledEvent>,
+                        log_events::<PlayerHitEvent>,
+                        log_events::<PlayerKilledEvent>,
+                        log_events::<CapsuleReleasedEvent>,
+                        log_events::<CapsuleCollisionEvent>,
+                        log_events::<LevelCompletedEvent>,
+                        log_events::<SpeedChangedEvent>,
+                        log_events::<UfoKilledEvent>,
+                    ),
+                    destroy_event_log_text,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, flush_event_log);
+    }
+}
+
+const LOG_DIR: &str = "logs";
+const FLUSH_INTERVAL: f32 = 1.;
+const TEXT_TIME: f32 = 2.5;
+
+//one line of the log
+#[derive(Serialize)]
+struct Record {
+    //seconds of game time since the session started
+    time: f32,
+    level: i32,
+    #[serde(flatten)]
+    entry: LogEntry,
+}
+
+//players are logged by slot, 0 for the first
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogEntry {
+    AlienKilled {
+        alien_type: AlienType,
+        x: f32,
+        y: f32,
+        player: Option<usize>,
+    },
+    PlayerHit {
+        player: Option<usize>,
+    },
+    PlayerKilled {
+        cause: DeathCause,
+    },
+    CapsuleReleased,
+    CapsuleCollision {
+        player: Option<usize>,
+    },
+    LevelCompleted,
+    SpeedChanged {
+        speed: f32,
+    },
+    UfoKilled {
+        points: i32,
+        by_fire: bool,
+        player: Option<usize>,
+    },
+}
+
+//an event that can be written to the log
+pub trait Loggable: Event {
+    fn entry(&self, player_query: &Query<&Player>) -> LogEntry;
+}
+
+fn slot(player_query: &Query<&Player>, player: Entity) -> Option<usize> {
+    player_query.get(player).ok().map(|player| player.slot)
+}
+
+impl Loggable for AlienKilledEvent {
+    fn entry(&self, player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::AlienKilled {
+            alien_type: self.alien_type,
+            x: self.location.x,
+            y: self.location.y,
+            player: slot(player_query, self.player),
+        }
+    }
+}
+
+impl Loggable for PlayerHitEvent {
+    fn entry(&self, player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::PlayerHit {
+            player: slot(player_query, self.player),
+        }
+    }
+}
+
+impl Loggable for PlayerKilledEvent {
+    fn entry(&self, _player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::PlayerKilled { cause: self.cause }
+    }
+}
+
+impl Loggable for CapsuleReleasedEvent {
+    fn entry(&self, _player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::CapsuleReleased
+    }
+}
+
+impl Loggable for CapsuleCollisionEvent {
+    fn entry(&self, player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::CapsuleCollision {
+            player: slot(player_query, self.player),
+        }
+    }
+}
+
+impl Loggable for LevelCompletedEvent {
+    fn entry(&self, _player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::LevelCompleted
+    }
+}
+
+impl Loggable for SpeedChangedEvent {
+    fn entry(&self, _player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::SpeedChanged { speed: self.speed }
+    }
+}
+
+impl Loggable for UfoKilledEvent {
+    fn entry(&self, player_query: &Query<&Player>) -> LogEntry {
+        LogEntry::UfoKilled {
+            points: self.points,
+            by_fire: self.by_fire,
+            player: slot(player_query, self.player),
+        }
+    }
+}
+
+//the session's log file, opened the first time something is logged
+#[derive(Resource, Default)]
+struct EventLog {
+    file: Option<BufWriter<Box<dyn Write + Send + Sync>>>,
+    path: Option<String>,
+    //the file could not be created or written, so logging is off until the next session
+    failed: bool,
+    since_flush: f32,
+}
+
+impl EventLog {
+    fn open(&mut self) {
+        if self.file.is_some() || self.failed {
+            return;
+        }
+        match storage::create_session_file(LOG_DIR, "jsonl") {
+            Some((path, file)) => {
+                self.file = Some(BufWriter::new(file));
+                self.path = Some(path);
+            }
+            None => self.failed = true,
+        }
+    }
+
+    fn write(&mut self, record: &Record) {
+        self.open();
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *file, record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| file.write_all(b"\n"));
+        if let Err(err) = result {
+            warn!("stopped logging events: {err}");
+            self.file = None;
+            self.failed = true;
+        }
+    }
+
+    fn flush(&mut self) {
+        self.since_flush = 0.;
+        if let Some(file) = &mut self.file
+            && let Err(err) = file.flush()
+        {
+            warn!("could not flush the event log: {err}");
+        }
+    }
+}
+
+#[derive(Component)]
+struct EventLogText {
+    time_remaining: f32,
+}
+
+fn toggle_event_log(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut event_log: ResMut<EventLog>,
+) {
+    if !keys.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+
+    settings.event_log = !settings.event_log;
+    let text = if settings.event_log {
+        event_log.open();
+        match &event_log.path {
+            Some(path) => Localized::new("event_log.on").arg("path", path),
+            None => Localized::new("event_log.unavailable"),
+        }
+    } else {
+        event_log.flush();
+        Localized::new("event_log.off")
+    };
+
+    commands.spawn((
+        widget::ui_center_root("Event Log"),
+        GlobalZIndex(2),
+        EventLogText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(widget::small_text(""), text)],
+    ));
+}
+
+fn log_events<E: Loggable>(
+    mut events: EventReader<E>,
+    mut event_log: ResMut<EventLog>,
+    settings: Res<Settings>,
+    score_manager: Res<ScoreManager>,
+    player_query: Query<&Player>,
+    time: Res<Time>,
+    screen: Option<Res<State<Screen>>>,
+) {
+    //nobody is playing the demo, so it says nothing about the difficulty
+    let demo = screen.is_some_and(|screen| *screen.get() == Screen::Demo);
+    if !settings.event_log || demo {
+        events.clear();
+        return;
+    }
+
+    for event in events.read() {
+        event_log.write(&Record {
+            time: time.elapsed_secs(),
+            level: score_manager.curr_level,
+            entry: event.entry(&player_query),
+        });
+    }
+}
+
+//whatever is still buffered when the app is torn down without an `AppExit` is flushed when the
+//writer is dropped
+fn flush_event_log(
+    mut event_log: ResMut<EventLog>,
+    mut exit_events: EventReader<AppExit>,
+    time: Res<Time<Real>>,
+) {
+    event_log.since_flush += time.delta_secs();
+    if exit_events.read().count() > 0 || event_log.since_flush > FLUSH_INTERVAL {
+        event_log.flush();
+    }
+}
+
+fn destroy_event_log_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut EventLogText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    //a log file that can still be read once the log has it
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    //logs the events sent to a world at level 3, and returns the lines written
+    fn log(screen: Screen, send: impl FnOnce(&mut World)) -> String {
+        let buffer = Buffer::default();
+        let mut world = World::new();
+        world.insert_resource(Settings {
+            event_log: true,
+            ..default()
+        });
+        world.insert_resource(ScoreManager {
+            curr_level: 3,
+            max_level: 3,
+        });
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(State::new(screen));
+        world.insert_resource(EventLog {
+            file: Some(BufWriter::new(Box::new(buffer.clone()))),
+            ..default()
+        });
+        world.init_resource::<Events<AlienKilledEvent>>();
+        world.init_resource::<Events<PlayerHitEvent>>();
+
+        send(&mut world);
+        world
+            .run_system_once(log_events::<AlienKilledEvent>)
+            .unwrap();
+        world.run_system_once(log_events::<PlayerHitEvent>).unwrap();
+        world.resource_mut::<EventLog>().flush();
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn player(world: &mut World, slot: usize) -> Entity {
+        world
+            .spawn(Player {
+                slot,
+                shoot_timer: 0.,
+                dead: false,
+                lives: 3,
+                score: 0,
+                main_gun_projectiles: 1,
+                side_gun_projectiles: 0,
+            })
+            .id()
+    }
+
+    #[test]
+    fn kills_and_hits_are_logged() {
+        let lines = log(Screen::Playing, |world| {
+            let player = player(world, 1);
+            world.send_event(AlienKilledEvent {
+                alien_type: AlienType::Queen,
+                location: Vec2::new(12., -40.5),
+                player,
+            });
+            world.send_event(PlayerHitEvent { player });
+        });
+        assert_eq!(
+            lines,
+            "{\"time\":0.0,\"level\":3,\"event\":\"alien_killed\",\"alien_type\":\"Queen\",\
+\"x\":12.0,\"y\":-40.5,\"player\":1}\n\
+{\"time\":0.0,\"level\":3,\"event\":\"player_hit\",\"player\":1}\n"
+        );
+    }
+
+    #[test]
+    fn the_demo_is_not_logged() {
+        let lines = log(Screen::Demo, |world| {
+            let player = player(world, 0);
+            world.send_event(PlayerHitEvent { player });
+        });
+        assert_eq!(lines, "");
+    }
+}
@@ -1,23 +1,134 @@
+//! The fire a killed alien leaves behind, which sets off the chain reaction.
+//!
+//! A fire grows from a small flame to a peak and burns down again over its life, fading out at
+//! the end, and drifts as it burns. How big and how long it burns depends on the alien that left
+//! it, as read from `effects/fire.fire.ron`. Its collider scales with the sprite, so it kills
+//! whatever the flame visibly touches.
+
 use bevy::prelude::*;
+use serde::Deserialize;
 
-use crate::alien::{Alien, Dead};
+use crate::accessibility::REDUCED_FLASHING_OPACITY;
+use crate::alien::{Alien, AlienType, Dead};
+use crate::collider::{Collider, Layers};
+use crate::fly_in::FlyingIn;
+use crate::pool::{Pool, PoolAppExt, Textures};
+use crate::preload::Preload;
 use crate::projectile::AlienKilledEvent;
 use crate::resolution;
+use crate::ron_asset::RonAssetPlugin;
+use crate::settings::Settings;
 
 pub struct FirePlugin;
 
-const FIRE_RADIUS: f32 = 10.;
-const FIRE_LIFESPAN: f32 = 2.;
+//the kill radius of a fire drawn at the sprite's own size
+const BASE_RADIUS: f32 = 4.;
+pub const COLLIDER: Collider = Collider::circle(BASE_RADIUS)
+    .on(Layers::FIRE)
+    .hits(Layers::ALIEN.union(Layers::BUNKER).union(Layers::UFO));
 
 impl Plugin for FirePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_fire_manager);
-        app.add_systems(Update, (spawn_fire, update_fire, update_fire_interactions));
+        app.add_plugins(RonAssetPlugin::<FireSettings>::new(&["fire.ron"]))
+            .insert_resource(FireSettings::default())
+            .add_systems(Startup, (setup_fire_manager, setup_fire_settings))
+            .add_systems(
+                Update,
+                (
+                    update_fire_settings,
+                    spawn_fire,
+                    update_fire,
+                    update_fire_interactions,
+                )
+                    .chain(),
+            );
+        app.add_pool::<Fire>(MAX_FIRES as usize);
     }
 }
 
+const SETTINGS_PATH: &str = "effects/fire.fire.ron";
 const MAX_FIRES: i32 = 20;
 
+#[derive(Asset, TypePath, Resource, Deserialize, Clone, PartialEq, Debug)]
+pub struct FireSettings {
+    //the radius a fire starts at, in the sprite's pixels
+    pub start_radius: f32,
+    //the share of its life a fire spends growing to its peak before it burns down
+    pub peak_at: f32,
+    //the share of its life at the end over which it fades out
+    pub fade: f32,
+    //world units per second, the same for every fire
+    pub drift: (f32, f32),
+    //past this many fires a new one merges into the nearest fire within `merge_distance` world
+    //units, or else takes the place of the fire closest to burning out
+    pub max_fires: i32,
+    pub merge_distance: f32,
+    pub worker: FireStrength,
+    pub soldier: FireStrength,
+    pub queen: FireStrength,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct FireStrength {
+    //the radius at its peak, in the sprite's pixels
+    pub radius: f32,
+    //seconds
+    pub lifespan: f32,
+}
+
+//the fire the game was tuned with, used until the settings have loaded
+impl Default for FireSettings {
+    fn default() -> Self {
+        Self {
+            start_radius: 1.5,
+            peak_at: 0.3,
+            fade: 0.35,
+            drift: (0., 0.),
+            max_fires: MAX_FIRES,
+            merge_distance: 24.,
+            worker: FireStrength {
+                radius: 5.,
+                lifespan: 2.,
+            },
+            soldier: FireStrength {
+                radius: 6.,
+                lifespan: 2.2,
+            },
+            queen: FireStrength {
+                radius: 10.,
+                lifespan: 2.6,
+            },
+        }
+    }
+}
+
+impl FireSettings {
+    pub fn strength(&self, alien_type: AlienType) -> FireStrength {
+        match alien_type {
+            AlienType::Soldier => self.soldier,
+            AlienType::Queen => self.queen,
+            AlienType::Worker | AlienType::Empty => self.worker,
+        }
+    }
+
+    //the radius of a fire `progress` of the way through its life, from 0 to 1
+    fn radius(&self, peak_radius: f32, progress: f32) -> f32 {
+        let peak_at = self.peak_at.clamp(f32::EPSILON, 1.);
+        if progress < peak_at {
+            self.start_radius.lerp(peak_radius, progress / peak_at)
+        } else {
+            peak_radius * ((1. - progress) / (1. - peak_at).max(f32::EPSILON)).clamp(0., 1.)
+        }
+    }
+
+    fn alpha(&self, progress: f32) -> f32 {
+        ((1. - progress) / self.fade.max(f32::EPSILON)).clamp(0., 1.)
+    }
+}
+
+#[derive(Resource)]
+struct FireSettingsHandle(Handle<FireSettings>);
+
 #[derive(Resource)]
 struct FireManager {
     num_fires: i32,
@@ -27,71 +138,164 @@ fn setup_fire_manager(mut commands: Commands) {
     commands.insert_resource(FireManager { num_fires: 0 });
 }
 
+fn setup_fire_settings(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preload: ResMut<Preload>,
+) {
+    commands.insert_resource(FireSettingsHandle(
+        preload.load(&asset_server, SETTINGS_PATH),
+    ));
+}
+
+fn update_fire_settings(
+    settings_handle: Res<FireSettingsHandle>,
+    assets: Res<Assets<FireSettings>>,
+    mut settings: ResMut<FireSettings>,
+) {
+    let Some(next) = assets.get(&settings_handle.0) else {
+        return;
+    };
+    settings.set_if_neq(next.clone());
+}
+
 #[derive(Component)]
 pub struct Fire {
+    //set to zero or below to put the fire out
     pub time_remaining: f32,
+    pub lifespan: f32,
+    pub peak_radius: f32,
+    //the player credited with the aliens this fire kills
+    pub player: Entity,
+}
+
+impl Fire {
+    fn new(strength: FireStrength, player: Entity) -> Self {
+        Self {
+            time_remaining: strength.lifespan,
+            lifespan: strength.lifespan.max(f32::EPSILON),
+            peak_radius: strength.radius,
+            player,
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        (1. - self.time_remaining / self.lifespan).clamp(0., 1.)
+    }
 }
 
 fn spawn_fire(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    textures: Res<Textures>,
+    mut pool: ResMut<Pool<Fire>>,
     mut alien_killed_events: EventReader<AlienKilledEvent>,
-    resolution: Res<resolution::Resolution>,
+    settings: Res<FireSettings>,
     mut fire_manager: ResMut<FireManager>,
+    mut fire_query: Query<(&mut Fire, &mut Transform)>,
 ) {
     for event in alien_killed_events.read() {
-        if fire_manager.num_fires < MAX_FIRES {
-            let fire_image = asset_server.load("images/fire.png");
-            commands.spawn((
-                Sprite {
-                    image: fire_image,
-                    ..Default::default()
-                },
-                Transform::from_xyz(event.location.x, event.location.y, 5.0)
-                    .with_scale(Vec3::splat(resolution.pixel_ratio)),
-                Fire {
-                    time_remaining: FIRE_LIFESPAN,
-                },
-            ));
-
+        let strength = settings.strength(event.alien_type);
+        if fire_manager.num_fires < settings.max_fires {
+            pool.spawn(
+                &mut commands,
+                (
+                    Sprite {
+                        image: textures.fire.clone(),
+                        ..Default::default()
+                    },
+                    //sized by `update_fire` before it is drawn
+                    Transform::from_xyz(event.location.x, event.location.y, 5.0)
+                        .with_scale(Vec3::ZERO),
+                    Fire::new(strength, event.player),
+                    COLLIDER,
+                ),
+            );
             fire_manager.num_fires += 1;
+            continue;
+        }
+
+        //too many fires: flare up the nearest one, or rekindle the one closest to burning out here
+        let distance =
+            |transform: &Transform| transform.translation.truncate().distance(event.location);
+        let nearest = fire_query
+            .iter_mut()
+            .filter(|(_, transform)| distance(transform) < settings.merge_distance)
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)));
+        if let Some((mut fire, mut transform)) = nearest {
+            let merged = FireStrength {
+                radius: fire.peak_radius.max(strength.radius),
+                lifespan: strength.lifespan,
+            };
+            *fire = Fire::new(merged, fire.player);
+            //back at its peak rather than starting small again
+            fire.time_remaining = fire.lifespan * (1. - settings.peak_at);
+            let midpoint = transform.translation.truncate().midpoint(event.location);
+            transform.translation = midpoint.extend(transform.translation.z);
+            continue;
+        }
+        let oldest = fire_query
+            .iter_mut()
+            .min_by(|(a, _), (b, _)| a.time_remaining.total_cmp(&b.time_remaining));
+        if let Some((mut fire, mut transform)) = oldest {
+            *fire = Fire::new(strength, event.player);
+            transform.translation = event.location.extend(transform.translation.z);
         }
     }
 }
 
 fn update_fire(
     mut commands: Commands,
-    mut fire_query: Query<(Entity, &mut Fire)>,
+    mut fire_query: Query<(Entity, &mut Fire, &mut Transform, &mut Sprite)>,
+    mut pool: ResMut<Pool<Fire>>,
+    settings: Res<FireSettings>,
+    game_settings: Res<Settings>,
     time: Res<Time>,
+    resolution: Res<resolution::Resolution>,
     mut fire_manager: ResMut<FireManager>,
 ) {
-    for (entity, mut fire) in fire_query.iter_mut() {
+    let drift = Vec2::from(settings.drift) * time.delta_secs();
+    let opacity = if game_settings.accessibility.reduce_flashing {
+        REDUCED_FLASHING_OPACITY
+    } else {
+        1.
+    };
+    for (entity, mut fire, mut transform, mut sprite) in fire_query.iter_mut() {
         fire.time_remaining -= time.delta_secs();
 
         if fire.time_remaining < 0. {
-            commands.entity(entity).despawn();
+            pool.release(&mut commands, entity);
             fire_manager.num_fires -= 1;
+            continue;
         }
+
+        let progress = fire.progress();
+        let radius = settings.radius(fire.peak_radius, progress);
+        transform.scale = Vec3::splat(resolution.pixel_ratio * radius / BASE_RADIUS);
+        transform.translation += drift.extend(0.);
+        sprite.color = Color::WHITE.with_alpha(settings.alpha(progress) * opacity);
     }
 }
 
 fn update_fire_interactions(
-    mut alien_query: Query<(&mut Alien, &Transform), Without<Dead>>,
-    mut fire_query: Query<&Transform, With<Fire>>,
+    mut alien_query: Query<(&mut Alien, &Collider, &Transform), (Without<Dead>, Without<FlyingIn>)>,
+    mut fire_query: Query<(&Fire, &Collider, &Transform)>,
     mut events: EventWriter<AlienKilledEvent>,
 ) {
-    for (mut alien, alien_transform) in alien_query.iter_mut() {
-        for fire_transform in fire_query.iter_mut() {
-            let fire_pos = Vec2::new(fire_transform.translation.x, fire_transform.translation.y);
-
+    for (mut alien, alien_collider, alien_transform) in alien_query.iter_mut() {
+        for (fire, fire_collider, fire_transform) in fire_query.iter_mut() {
+            //a fire that has been put out only waits for `update_fire` to release it
+            if fire.time_remaining <= 0. {
+                continue;
+            }
             let alien_pos = Vec2::new(alien_transform.translation.x, alien_transform.translation.y);
 
-            if Vec2::distance(alien_pos, fire_pos) < FIRE_RADIUS {
+            if fire_collider.collides(fire_transform, alien_collider, alien_transform) {
                 alien.dead = true;
                 //commands.entity(alien_entity).despawn();
                 events.write(AlienKilledEvent {
                     alien_type: alien.alien_type,
                     location: alien_pos,
+                    player: fire.player,
                 });
             }
         }
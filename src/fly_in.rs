@@ -0,0 +1,229 @@
+//! New waves fly in from off screen to their places in the formation, along the paths described
+//! per level in `levels/entries.entries.ron`.
+//!
+//! Every alien of a wave follows the same spline, ending at its own place, and sets off a moment
+//! after the one before it. Aliens on the right half of the formation can fly the mirror image, so
+//! the wave pours in from both sides at once. The formation holds still until the last alien has
+//! arrived, and aliens that are still [`FlyingIn`] can neither shoot nor be hit.
+
+use std::collections::BTreeMap;
+
+use bevy::math::cubic_splines::{CubicCardinalSpline, CubicCurve, CubicGenerator};
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use serde::Deserialize;
+
+use crate::alien::{Alien, WaveStartedEvent};
+use crate::level_indicator::ScoreManager;
+use crate::level_table::{LevelKeyed, LevelTable};
+use crate::preload::Preload;
+use crate::ron_asset::RonAssetPlugin;
+use crate::title_screen::Screen;
+
+pub struct FlyInPlugin;
+
+impl Plugin for FlyInPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<EntryPatterns>::new(&["entries.ron"]))
+            .init_resource::<StartFlyIn>()
+            .add_systems(Startup, setup_entry_patterns)
+            .add_systems(Update, fly_in)
+            //once the level has gone up and the aliens are in place for the wave that is starting,
+            //and before anything is drawn where they were
+            .add_systems(
+                PostUpdate,
+                (queue_fly_in, start_fly_in)
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+const PATTERNS_PATH: &str = "levels/entries.entries.ron";
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct EntryPatterns {
+    pub patterns: BTreeMap<String, EntryPattern>,
+    pub levels: LevelTable<EntryLevel>,
+}
+
+#[derive(Deserialize)]
+pub struct EntryPattern {
+    //where the path starts and the points it bends through on its way to each alien's place, in
+    //world units. With no points the wave appears in place
+    pub path: Vec<(f32, f32)>,
+    //whether the right half of the formation flies the path mirrored left to right
+    #[serde(default)]
+    pub mirror: bool,
+    //seconds each alien takes to fly the path
+    pub duration: f32,
+    //seconds between one alien setting off and the next
+    pub stagger: f32,
+}
+
+#[derive(Deserialize)]
+pub struct EntryLevel {
+    //the first level this applies to, until the next one
+    pub level: i32,
+    pub pattern: String,
+}
+
+impl LevelKeyed for EntryLevel {
+    fn level(&self) -> i32 {
+        self.level
+    }
+}
+
+impl EntryPatterns {
+    pub fn at_level(&self, level: i32) -> Option<&EntryPattern> {
+        let entry = self.levels.at(level);
+        let pattern = self.patterns.get(&entry.pattern);
+        if pattern.is_none() {
+            warn!("there is no entry pattern called {}", entry.pattern);
+        }
+        pattern
+    }
+}
+
+//an alien on its way to its place in the formation, `Alien::original_position`
+#[derive(Component)]
+pub struct FlyingIn {
+    //seconds until it sets off
+    delay: f32,
+    //how far along the path it is, from 0 to 1
+    progress: f32,
+    duration: f32,
+    path: CubicCurve<Vec2>,
+}
+
+#[derive(Resource)]
+struct EntryPatternsHandle(Handle<EntryPatterns>);
+
+//set when a wave needs to fly in, including the first
+#[derive(Resource)]
+struct StartFlyIn(bool);
+
+impl Default for StartFlyIn {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn setup_entry_patterns(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preload: ResMut<Preload>,
+) {
+    commands.insert_resource(EntryPatternsHandle(
+        preload.load(&asset_server, PATTERNS_PATH),
+    ));
+}
+
+fn queue_fly_in(
+    mut wave_started_events: EventReader<WaveStartedEvent>,
+    mut start: ResMut<StartFlyIn>,
+) {
+    if wave_started_events.read().count() > 0 {
+        start.0 = true;
+    }
+}
+
+fn start_fly_in(
+    mut commands: Commands,
+    mut start: ResMut<StartFlyIn>,
+    patterns_handle: Res<EntryPatternsHandle>,
+    patterns: Res<Assets<EntryPatterns>>,
+    score_manager: Res<ScoreManager>,
+    screen: Option<Res<State<Screen>>>,
+    mut alien_query: Query<(Entity, &Alien, &mut Transform)>,
+) {
+    if !start.0 {
+        return;
+    }
+    let Some(patterns) = patterns.get(&patterns_handle.0) else {
+        return;
+    };
+    start.0 = false;
+
+    //a wave that starts over before it has flown in starts from the beginning, or in place
+    for (entity, ..) in alien_query.iter() {
+        commands.entity(entity).remove::<FlyingIn>();
+    }
+    //the wave being edited stays where the editor can see it
+    if screen.is_some_and(|screen| *screen.get() == Screen::Editor) {
+        return;
+    }
+    let Some(pattern) = patterns.at_level(score_manager.curr_level) else {
+        return;
+    };
+    if pattern.path.is_empty() {
+        return;
+    }
+
+    //top row first, and the outside of each row before the middle
+    let mut aliens: Vec<_> = alien_query.iter_mut().collect();
+    aliens.sort_by(|(_, a, _), (_, b, _)| {
+        let (a, b) = (a.original_position, b.original_position);
+        b.y.total_cmp(&a.y).then(b.x.abs().total_cmp(&a.x.abs()))
+    });
+
+    //with a mirrored path each half of the formation sets off on its own
+    let mut set_off = [0, 0];
+    for (entity, alien, transform) in aliens.iter_mut() {
+        let place = alien.original_position.truncate();
+        let mirrored = pattern.mirror && place.x > 0.;
+        let side = usize::from(mirrored);
+        let path = pattern
+            .path
+            .iter()
+            .map(|&(x, y)| Vec2::new(if mirrored { -x } else { x }, y))
+            .chain(std::iter::once(place));
+        let Ok(path) = CubicCardinalSpline::new_catmull_rom(path).to_curve() else {
+            continue;
+        };
+
+        transform.translation = path.position(0.).extend(transform.translation.z);
+        commands.entity(*entity).insert(FlyingIn {
+            delay: set_off[side] as f32 * pattern.stagger,
+            progress: 0.,
+            duration: pattern.duration.max(f32::EPSILON),
+            path,
+        });
+        set_off[side] += 1;
+    }
+}
+
+fn fly_in(
+    mut commands: Commands,
+    mut alien_query: Query<(Entity, &Alien, &mut FlyingIn, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, alien, mut flying, mut transform) in alien_query.iter_mut() {
+        if flying.delay > 0. {
+            flying.delay -= time.delta_secs();
+            continue;
+        }
+        flying.progress += time.delta_secs() / flying.duration;
+        if flying.progress >= 1. {
+            transform.translation = alien.original_position;
+            commands.entity(entity).remove::<FlyingIn>();
+            continue;
+        }
+        let t = flying.progress * flying.path.segments().len() as f32;
+        transform.translation = flying.path.position(t).extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_levels_have_their_patterns() {
+        let patterns: EntryPatterns =
+            ron::de::from_str(include_str!("../assets/levels/entries.entries.ron")).unwrap();
+        for level in 1..=30 {
+            assert!(patterns.at_level(level).is_some(), "level {level}");
+        }
+    }
+}
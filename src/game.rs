@@ -1,33 +1,101 @@
 use bevy::prelude::*;
 
+use crate::accessibility;
+use crate::achievements;
 use crate::alien;
 use crate::alien_projectile;
+use crate::alternating;
+use crate::autopilot;
+use crate::bunker;
 use crate::capsule;
+use crate::collider;
+#[cfg(feature = "dev")]
+use crate::console;
+#[cfg(feature = "dev")]
+use crate::dev_tools;
+use crate::difficulty;
+use crate::endless;
+use crate::event_log;
 use crate::fire;
+use crate::fly_in;
 use crate::game_audio;
+use crate::game_mode;
+use crate::hud;
+use crate::knockback;
 use crate::level_indicator;
+use crate::localization;
 use crate::player;
+use crate::pool;
+use crate::preload;
 use crate::projectile;
 use crate::resolution;
+use crate::rng;
+use crate::settings;
 use crate::star_field;
+use crate::title_screen;
+use crate::ufo;
+#[cfg(feature = "dev")]
+use crate::wave_editor;
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
-            alien::AlienPlugin,
-            alien_projectile::AlienProjectilePlugin,
-            capsule::CapsulePlugin,
-            fire::FirePlugin,
+            GameplayPlugin,
+            accessibility::AccessibilityPlugin,
+            achievements::AchievementsPlugin,
+            event_log::EventLogPlugin,
             game_audio::GameAudioPlugin,
-            level_indicator::LevelIndicatorPlugin,
-            resolution::ResolutionPlugin,
-            player::PlayerPlugin,
-            projectile::ProjectilePlugin,
+            hud::HudPlugin,
+            localization::LocalizationPlugin,
             star_field::StarFieldPlugin,
+            autopilot::AutopilotPlugin,
+            title_screen::TitleScreenPlugin,
         ))
         .add_systems(Startup, setup_scene);
+
+        #[cfg(feature = "dev")]
+        app.add_plugins((
+            console::ConsolePlugin,
+            dev_tools::DevToolsPlugin,
+            wave_editor::WaveEditorPlugin,
+        ));
+    }
+}
+
+//the rules of the game, without the window, audio or title screen around them. `env` runs this
+//on its own
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<rng::GameRng>()
+            .init_resource::<preload::Preload>()
+            .add_plugins((
+                alien::AlienPlugin,
+                alien_projectile::AlienProjectilePlugin,
+                bunker::BunkerPlugin,
+                capsule::CapsulePlugin,
+                collider::ColliderPlugin,
+                difficulty::DifficultyPlugin,
+                fire::FirePlugin,
+                fly_in::FlyInPlugin,
+                level_indicator::LevelIndicatorPlugin,
+                resolution::ResolutionPlugin,
+                settings::SettingsPlugin,
+                player::PlayerPlugin,
+                pool::PoolPlugin,
+                projectile::ProjectilePlugin,
+                ufo::UfoPlugin,
+            ))
+            .add_plugins(knockback::KnockbackPlugin)
+            //the ways to play
+            .add_plugins((
+                alternating::AlternatingPlugin,
+                endless::EndlessPlugin,
+                game_mode::GameModePlugin,
+            ));
     }
 }
 
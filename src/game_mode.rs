@@ -0,0 +1,148 @@
+//! How many people are playing and how they share the screen.
+//!
+//! Press 1 for a single player, 2 for two players in co-op or 3 for two players taking turns.
+//! Switching starts the run over. The choice is saved in [`Settings`].
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::alien::AlienManager;
+use crate::alternating::Turns;
+use crate::level_indicator::ScoreManager;
+use crate::localization::Localized;
+use crate::player::{self, Player};
+use crate::pool::InPlay;
+use crate::resolution;
+use crate::settings::Settings;
+use crate::widget;
+
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NewRunEvent>().add_systems(
+            Update,
+            (switch_mode, start_new_run, destroy_mode_text).chain(),
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum GameMode {
+    #[default]
+    Single,
+    //two ships on screen at once, each with their own lives
+    CoOp,
+    //two players taking turns, each with their own wave, see `alternating`
+    Alternating,
+}
+
+impl GameMode {
+    pub fn players(self) -> usize {
+        match self {
+            GameMode::Single => 1,
+            GameMode::CoOp | GameMode::Alternating => 2,
+        }
+    }
+
+    pub fn takes_turns(self) -> bool {
+        self == GameMode::Alternating
+    }
+
+    //a single player starts over as soon as they are hit
+    pub fn lives(self) -> i32 {
+        match self {
+            GameMode::Single => 1,
+            GameMode::CoOp | GameMode::Alternating => 3,
+        }
+    }
+
+    //the key of its name in the string tables
+    fn name(self) -> &'static str {
+        match self {
+            GameMode::Single => "mode.single",
+            GameMode::CoOp => "mode.co_op",
+            GameMode::Alternating => "mode.alternating",
+        }
+    }
+}
+
+//starts the run over from the first level with fresh ships for the current mode
+#[derive(Event, Debug)]
+pub struct NewRunEvent {}
+
+#[derive(Component)]
+struct ModeText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.5;
+
+fn switch_mode(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut new_run_events: EventWriter<NewRunEvent>,
+) {
+    let mode = if keys.just_pressed(KeyCode::Digit1) {
+        GameMode::Single
+    } else if keys.just_pressed(KeyCode::Digit2) {
+        GameMode::CoOp
+    } else if keys.just_pressed(KeyCode::Digit3) {
+        GameMode::Alternating
+    } else {
+        return;
+    };
+
+    settings.mode = mode;
+    new_run_events.write(NewRunEvent {});
+
+    commands.spawn((
+        widget::ui_center_root("Game Mode"),
+        GlobalZIndex(2),
+        ModeText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(widget::large_text(""), Localized::new(mode.name()))],
+    ));
+}
+
+fn start_new_run(
+    mut commands: Commands,
+    mut new_run_events: EventReader<NewRunEvent>,
+    asset_server: Res<AssetServer>,
+    resolution: Res<resolution::Resolution>,
+    settings: Res<Settings>,
+    mut score_manager: ResMut<ScoreManager>,
+    mut alien_manager: ResMut<AlienManager>,
+    mut turns: ResMut<Turns>,
+    player_query: Query<Entity, With<Player>>,
+    mut in_play: InPlay,
+) {
+    if new_run_events.read().count() == 0 {
+        return;
+    }
+
+    score_manager.curr_level = 1;
+    alien_manager.reset = true;
+    turns.reset();
+    //the shots, fires and capsules belong to players that are about to be replaced
+    in_play.clear(&mut commands);
+    for entity in player_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    player::spawn_players(&mut commands, &asset_server, &resolution, settings.mode);
+}
+
+fn destroy_mode_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut ModeText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Each player's score and lives along the top of the screen.
+
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::localization::{Localization, Localized};
+use crate::player::Player;
+use crate::widget;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_hud)
+            .add_systems(Update, update_hud);
+    }
+}
+
+//the player in `slot` is shown on the left for the first player and on the right for the second
+#[derive(Component)]
+struct PlayerStatusText {
+    slot: usize,
+}
+
+fn setup_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Hud"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            justify_content: JustifyContent::SpaceBetween,
+            padding: UiRect::all(Px(10.0)),
+            ..default()
+        },
+        GlobalZIndex(1),
+        Pickable::IGNORE,
+        children![
+            (widget::small_text(""), PlayerStatusText { slot: 0 }),
+            (widget::small_text(""), PlayerStatusText { slot: 1 }),
+        ],
+    ));
+}
+
+//written every frame rather than through a `Localized` component, as the score changes so often
+fn update_hud(
+    localization: Res<Localization>,
+    player_query: Query<&Player>,
+    mut text_query: Query<(&PlayerStatusText, &mut Text, &mut TextFont)>,
+) {
+    let num_players = player_query.iter().count();
+    for (status, mut text, mut font) in text_query.iter_mut() {
+        let Some(player) = player_query
+            .iter()
+            .find(|player| player.slot == status.slot)
+        else {
+            text.0.clear();
+            continue;
+        };
+
+        //a single player starts over when hit, so there are no lives to show
+        text.0 = if num_players > 1 {
+            localization.get(
+                &Localized::new("hud.player")
+                    .arg("player", player.slot + 1)
+                    .arg("score", format!("{:06}", player.score))
+                    .count(player.lives.max(0).into()),
+            )
+        } else {
+            format!("{:06}", player.score)
+        };
+        if font.font != localization.font() {
+            font.font = localization.font();
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Explosions knock the living aliens around them out of formation.
+//!
+//! A knocked alien flies off with the blast and springs back to its place in the formation, which
+//! carries on marching without it. On the way it can bump into its neighbours and pass some of its
+//! speed on to them, and whatever it is pushed into, fire included, hits it as usual.
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+
+use crate::alien::{Alien, Dead};
+use crate::collider::{Collider, Layers};
+use crate::fly_in::FlyingIn;
+use crate::projectile::AlienKilledEvent;
+
+pub struct KnockbackPlugin;
+
+impl Plugin for KnockbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (knock_aliens, update_knockback).chain());
+    }
+}
+
+//how far from an explosion it reaches, and the speed it gives an alien right next to it
+const RADIUS: f32 = 90.;
+const IMPULSE: f32 = 400.;
+//how hard a knocked alien is pulled back to its place, and how quickly it stops bouncing
+const STIFFNESS: f32 = 40.;
+const DAMPING: f32 = 7.;
+//closer and slower than this and it is back in formation
+const SETTLED_DISTANCE: f32 = 0.5;
+const SETTLED_SPEED: f32 = 5.;
+//two aliens coming together faster than this bump, and the share of that speed that is passed on
+const MIN_BUMP_SPEED: f32 = 40.;
+const TRANSFER: f32 = 0.6;
+
+//an alien out of its place in the formation
+#[derive(Component)]
+pub struct Knockback {
+    //from its place, in world units
+    pub offset: Vec2,
+    pub velocity: Vec2,
+}
+
+fn knock_aliens(
+    mut commands: Commands,
+    mut alien_killed_events: EventReader<AlienKilledEvent>,
+    mut alien_query: Query<
+        (
+            Entity,
+            &Alien,
+            &Collider,
+            &Transform,
+            Option<&mut Knockback>,
+        ),
+        (Without<Dead>, Without<FlyingIn>),
+    >,
+) {
+    let mut pushes = EntityHashMap::<Vec2>::default();
+
+    for event in alien_killed_events.read() {
+        for (entity, alien, _, transform, _) in alien_query.iter() {
+            let away = transform.translation.truncate() - event.location;
+            let distance = away.length();
+            if alien.dead || distance >= RADIUS {
+                continue;
+            }
+            *pushes.entry(entity).or_default() +=
+                away.normalize_or_zero() * IMPULSE * (1. - distance / RADIUS);
+        }
+    }
+
+    let aliens: Vec<_> = alien_query
+        .iter()
+        .filter(|(_, alien, ..)| !alien.dead)
+        .map(|(entity, _, collider, transform, knockback)| {
+            let velocity = knockback.map_or(Vec2::ZERO, |knockback| knockback.velocity);
+            (entity, collider, transform, velocity)
+        })
+        .collect();
+    for (index, &(entity, collider, transform, velocity)) in aliens.iter().enumerate() {
+        for &(other, other_collider, other_transform, other_velocity) in &aliens[index + 1..] {
+            if !collider
+                .hits(Layers::ALIEN)
+                .collides(transform, other_collider, other_transform)
+            {
+                continue;
+            }
+            let towards = (other_transform.translation - transform.translation)
+                .truncate()
+                .normalize_or_zero();
+            let closing = (velocity - other_velocity).dot(towards);
+            if closing > MIN_BUMP_SPEED {
+                let transfer = towards * closing * TRANSFER;
+                *pushes.entry(entity).or_default() -= transfer;
+                *pushes.entry(other).or_default() += transfer;
+            }
+        }
+    }
+
+    for (entity, push) in pushes {
+        match alien_query.get_mut(entity) {
+            Ok((.., Some(mut knockback))) => knockback.velocity += push,
+            _ => {
+                commands.entity(entity).try_insert(Knockback {
+                    offset: Vec2::ZERO,
+                    velocity: push,
+                });
+            }
+        }
+    }
+}
+
+fn update_knockback(
+    mut commands: Commands,
+    mut alien_query: Query<(Entity, &mut Knockback, &mut Transform), Without<Dead>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    for (entity, mut knockback, mut transform) in alien_query.iter_mut() {
+        let pull = -STIFFNESS * knockback.offset - DAMPING * knockback.velocity;
+        knockback.velocity += pull * delta;
+        let step = knockback.velocity * delta;
+        knockback.offset += step;
+        transform.translation += step.extend(0.);
+
+        if knockback.offset.length() < SETTLED_DISTANCE
+            && knockback.velocity.length() < SETTLED_SPEED
+        {
+            transform.translation -= knockback.offset.extend(0.);
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use bevy::prelude::*;
 
+use crate::alien::AlienManager;
+use crate::console::{self, ConsoleAppExt};
+use crate::endless::EndlessMode;
+use crate::localization::Localized;
 use crate::widget;
 
 pub struct LevelIndicatorPlugin;
 
 #[derive(Component)]
-struct LevelTextParent {}
+pub struct LevelTextParent {}
 
 #[derive(Component)]
 struct LevelText {
@@ -26,9 +30,28 @@ impl Plugin for LevelIndicatorPlugin {
         app.add_systems(Startup, setup_parent_widget);
         app.add_systems(Update, (update_level_complete, destroy_level_complete));
         app.add_event::<LevelCompletedEvent>();
+        app.add_console_command("level <n>", jump_to_level);
     }
 }
 
+//starts the wave over at another level
+fn jump_to_level(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let level: i32 = console::arg(args, 0, "level")?;
+    if level < 1 {
+        return Err(String::from("levels start at 1"));
+    }
+
+    let mut score_manager = world.resource_mut::<ScoreManager>();
+    score_manager.curr_level = level;
+    score_manager.max_level = score_manager.max_level.max(level);
+    world.resource_mut::<AlienManager>().reset = true;
+    //endless mode generates the formation for the new level
+    if let Some(mut endless) = world.get_resource_mut::<EndlessMode>() {
+        endless.wave_level = 0;
+    }
+    Ok(format!("level {level}"))
+}
+
 #[derive(Event)]
 pub struct LevelCompletedEvent {}
 
@@ -53,7 +76,7 @@ fn setup_parent_widget(mut commands: Commands) {
 
 const TIME_REMAINING: f32 = 1.7;
 
-fn update_level_complete(
+pub fn update_level_complete(
     mut commands: Commands,
     parent_query: Query<Entity, With<LevelTextParent>>,
     mut events: EventReader<LevelCompletedEvent>,
@@ -68,10 +91,10 @@ fn update_level_complete(
 
             let child = commands
                 .spawn((
-                    widget::large_text(format!(
-                        "Level {} (Max: {})",
-                        score_manager.curr_level, score_manager.max_level
-                    )),
+                    widget::large_text(""),
+                    Localized::new("level")
+                        .arg("level", score_manager.curr_level)
+                        .arg("max", score_manager.max_level),
                     LevelText {
                         time_remaining: TIME_REMAINING,
                     },
@@ -0,0 +1,139 @@
+//! Values that change with the level, such as the difficulty curve, the bunker layouts and the
+//! entry patterns.
+//!
+//! A [`LevelTable`] is written in its data file as a list of entries in increasing level order.
+//! Each entry applies from its own level until the next entry's, and a list that is empty or out of
+//! order is rejected when the file loads.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+//an entry of a `LevelTable`
+pub trait LevelKeyed {
+    //the first level the entry applies to
+    fn level(&self) -> i32;
+}
+
+//a `(level, value)` pair, as in the difficulty curve
+impl<T> LevelKeyed for (i32, T) {
+    fn level(&self) -> i32 {
+        self.0
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(
+    try_from = "Vec<T>",
+    bound(deserialize = "T: Deserialize<'de> + LevelKeyed")
+)]
+pub struct LevelTable<T> {
+    entries: Vec<T>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LevelTableError {
+    Empty,
+    //the level of the entry that does not come after the one before it
+    OutOfOrder(i32),
+}
+
+impl fmt::Display for LevelTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelTableError::Empty => write!(f, "there are no levels"),
+            LevelTableError::OutOfOrder(level) => {
+                write!(f, "level {level} is not after the level before it")
+            }
+        }
+    }
+}
+
+impl<T: LevelKeyed> TryFrom<Vec<T>> for LevelTable<T> {
+    type Error = LevelTableError;
+
+    fn try_from(entries: Vec<T>) -> Result<Self, Self::Error> {
+        if entries.is_empty() {
+            return Err(LevelTableError::Empty);
+        }
+        if let Some(pair) = entries
+            .windows(2)
+            .find(|pair| pair[1].level() <= pair[0].level())
+        {
+            return Err(LevelTableError::OutOfOrder(pair[1].level()));
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl<T: LevelKeyed> LevelTable<T> {
+    //the entry for `level`. Levels before the first entry use the first one
+    pub fn at(&self, level: i32) -> &T {
+        let after = self.after(level);
+        &self.entries[after.saturating_sub(1)]
+    }
+
+    //the entries either side of `level` and how far it is from the first to the second, from 0 to
+    //1. Levels before the first entry or after the last use that entry on both sides
+    pub fn around(&self, level: i32) -> (&T, &T, f32) {
+        let after = self.after(level);
+        if after == 0 {
+            return (&self.entries[0], &self.entries[0], 0.);
+        }
+        let Some(to) = self.entries.get(after) else {
+            let last = &self.entries[after - 1];
+            return (last, last, 0.);
+        };
+        let from = &self.entries[after - 1];
+        let t = (level - from.level()) as f32 / (to.level() - from.level()) as f32;
+        (from, to, t)
+    }
+
+    //the index of the first entry after `level`
+    fn after(&self, level: i32) -> usize {
+        self.entries.partition_point(|entry| entry.level() <= level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(levels: &[i32]) -> Result<LevelTable<(i32, char)>, LevelTableError> {
+        LevelTable::try_from(levels.iter().map(|level| (*level, 'x')).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn rejects_empty_and_unordered_tables() {
+        assert_eq!(table(&[]).unwrap_err(), LevelTableError::Empty);
+        assert_eq!(
+            table(&[1, 5, 3]).unwrap_err(),
+            LevelTableError::OutOfOrder(3)
+        );
+        assert_eq!(
+            table(&[1, 5, 5]).unwrap_err(),
+            LevelTableError::OutOfOrder(5)
+        );
+        assert!(table(&[1, 5, 10]).is_ok());
+    }
+
+    #[test]
+    fn rejected_when_deserialized() {
+        let err = ron::de::from_str::<LevelTable<(i32, char)>>("[(5, 'a'), (1, 'b')]").unwrap_err();
+        assert!(err.to_string().contains("level 1 is not after"), "{err}");
+        assert!(ron::de::from_str::<LevelTable<(i32, char)>>("[]").is_err());
+    }
+
+    #[test]
+    fn entries_apply_until_the_next_one() {
+        let table = LevelTable::try_from(vec![(3, 'a'), (5, 'b'), (10, 'c')]).unwrap();
+        let at = |level| table.at(level).1;
+        assert_eq!(at(1), 'a');
+        assert_eq!(at(3), 'a');
+        assert_eq!(at(4), 'a');
+        assert_eq!(at(5), 'b');
+        assert_eq!(at(9), 'b');
+        assert_eq!(at(10), 'c');
+        assert_eq!(at(50), 'c');
+    }
+}
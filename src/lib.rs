@@ -0,0 +1,51 @@
+//! The game as a library, so it can be driven without a window by [`env`].
+
+pub mod accessibility;
+pub mod achievements;
+pub mod adaptive_music;
+pub mod alien;
+pub mod alien_layouts;
+pub mod alien_projectile;
+pub mod alternating;
+pub mod autopilot;
+pub mod bunker;
+pub mod capsule;
+pub mod collider;
+pub mod console;
+#[cfg(feature = "dev")]
+pub mod dev_tools;
+pub mod difficulty;
+pub mod endless;
+pub mod env;
+pub mod event_log;
+pub mod fire;
+pub mod fly_in;
+pub mod game;
+pub mod game_audio;
+pub mod game_mode;
+pub mod hud;
+pub mod knockback;
+pub mod level_indicator;
+pub mod level_table;
+pub mod localization;
+pub mod midi;
+pub mod music_playlist;
+pub mod player;
+pub mod pool;
+pub mod preload;
+pub mod projectile;
+pub mod resolution;
+pub mod rng;
+pub mod ron_asset;
+pub mod settings;
+pub mod sound_effects;
+pub mod star_field;
+pub mod storage;
+pub mod title_screen;
+pub mod ufo;
+#[cfg(feature = "dev")]
+pub mod wave_editor;
+pub mod wave_file;
+pub mod wave_generator;
+pub mod weapon;
+pub mod widget;
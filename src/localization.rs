@@ -0,0 +1,436 @@
+//! The game's text in the player's language, from the string tables in `locale/`.
+//!
+//! Text on screen is spawned with a [`Localized`] key rather than the words themselves, and is
+//! filled in from the table for [`Settings::language`]. Tables fill `{name}` placeholders from the
+//! values the key was given, and plural keys pick their form by the language's [`PluralRule`].
+//! Keys a table is missing come from the English table, which is built in so there is always
+//! something to show. Press I to switch language; text already on screen follows.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy::window::PrimaryWindow;
+use serde::Deserialize;
+
+use crate::ron_asset::RonAssetPlugin;
+use crate::settings::Settings;
+use crate::widget;
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<StringTable>::new(&["strings.ron"]))
+            .init_resource::<Localization>()
+            .add_systems(Startup, setup_string_tables)
+            .add_systems(
+                Update,
+                (switch_language, update_localization, destroy_language_text).chain(),
+            )
+            //after everything that spawns or changes text, and before it is laid out
+            .add_systems(
+                PostUpdate,
+                (localize_text, localize_text_2d, update_window_title).before(UiSystem::Prepare),
+            );
+    }
+}
+
+//the languages there are tables for, in the order I goes through them
+pub const LANGUAGES: [&str; 2] = ["en", "fr"];
+pub const ENGLISH: &str = "en";
+const BUILT_IN_ENGLISH: &str = include_str!("../assets/locale/en.strings.ron");
+
+#[derive(Asset, TypePath, Deserialize, Clone)]
+pub struct StringTable {
+    #[serde(default)]
+    pub plural_rule: PluralRule,
+    //for letters the built in font lacks, as it only has ASCII
+    #[serde(default)]
+    pub font: Option<String>,
+    pub strings: HashMap<String, String>,
+    #[serde(default)]
+    pub plurals: HashMap<String, PluralForms>,
+}
+
+//the forms a language's `PluralRule` can pick, falling back to `other`
+#[derive(Deserialize, Clone)]
+pub struct PluralForms {
+    #[serde(default)]
+    pub one: Option<String>,
+    #[serde(default)]
+    pub few: Option<String>,
+    #[serde(default)]
+    pub many: Option<String>,
+    pub other: String,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PluralRule {
+    //1 is singular, as in English, German and Spanish
+    #[default]
+    OneOther,
+    //0 and 1 are singular, as in French
+    ZeroOrOne,
+    //no plural forms, as in Japanese and Chinese
+    OtherOnly,
+    //1, 21, 31... are `one`, 2-4, 22-24... are `few` and the rest `many`, as in Russian
+    EastSlavic,
+}
+
+#[derive(PartialEq, Debug)]
+enum PluralCategory {
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralRule {
+    fn category(self, count: i64) -> PluralCategory {
+        let n = count.unsigned_abs();
+        match self {
+            PluralRule::OneOther if n == 1 => PluralCategory::One,
+            PluralRule::ZeroOrOne if n <= 1 => PluralCategory::One,
+            PluralRule::OneOther | PluralRule::ZeroOrOne | PluralRule::OtherOnly => {
+                PluralCategory::Other
+            }
+            PluralRule::EastSlavic => {
+                let (units, tens) = (n % 10, n % 100);
+                if units == 1 && tens != 11 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&units) && !(12..=14).contains(&tens) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Many
+                }
+            }
+        }
+    }
+}
+
+impl PluralForms {
+    fn pick(&self, category: PluralCategory) -> &str {
+        let form = match category {
+            PluralCategory::One => &self.one,
+            PluralCategory::Few => &self.few,
+            PluralCategory::Many => &self.many,
+            PluralCategory::Other => &None,
+        };
+        form.as_deref().unwrap_or(&self.other)
+    }
+}
+
+//text shown in the player's language, by its key in the string tables
+#[derive(Component, Clone, PartialEq, Debug)]
+pub struct Localized {
+    key: Cow<'static, str>,
+    //values for the placeholders, e.g. `("level", "3")` for `{level}`
+    args: Vec<(&'static str, String)>,
+    //picks the plural form, and fills `{count}`
+    count: Option<i64>,
+    //shown when no table has the key, e.g. names from other data files
+    fallback: Option<String>,
+}
+
+impl Localized {
+    pub fn new(key: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            key: key.into(),
+            args: Vec::new(),
+            count: None,
+            fallback: None,
+        }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl Display) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+
+    pub fn count(mut self, count: i64) -> Self {
+        self.count = Some(count);
+        self.arg("count", count)
+    }
+
+    pub fn or(mut self, fallback: impl Into<String>) -> Self {
+        self.fallback = Some(fallback.into());
+        self
+    }
+}
+
+#[derive(Resource)]
+pub struct Localization {
+    //the table for `Settings::language` once it has loaded
+    current: Option<StringTable>,
+    english: StringTable,
+    font: Handle<Font>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            current: None,
+            english: ron::from_str(BUILT_IN_ENGLISH)
+                .expect("the built in English strings should be valid"),
+            font: Handle::default(),
+        }
+    }
+}
+
+impl Localization {
+    pub fn get(&self, text: &Localized) -> String {
+        let mut tables = self.current.iter().chain(std::iter::once(&self.english));
+        let found = match text.count {
+            Some(count) => tables.find_map(|table| {
+                let forms = table.plurals.get(text.key.as_ref())?;
+                Some(forms.pick(table.plural_rule.category(count)))
+            }),
+            None => {
+                tables.find_map(|table| table.strings.get(text.key.as_ref()).map(String::as_str))
+            }
+        };
+        let Some(found) = found.or(text.fallback.as_deref()) else {
+            return text.key.to_string();
+        };
+
+        let mut filled = found.to_string();
+        for (name, value) in &text.args {
+            filled = filled.replace(&format!("{{{name}}}"), value);
+        }
+        filled
+    }
+
+    //the font for the current language
+    pub fn font(&self) -> Handle<Font> {
+        self.font.clone()
+    }
+}
+
+#[derive(Resource)]
+struct StringTableHandles(Vec<Handle<StringTable>>);
+
+fn setup_string_tables(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = LANGUAGES
+        .iter()
+        .map(|language| asset_server.load(format!("locale/{language}.strings.ron")))
+        .collect();
+    commands.insert_resource(StringTableHandles(handles));
+}
+
+#[derive(Component)]
+struct LanguageText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.5;
+
+fn switch_language(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyI) {
+        return;
+    }
+
+    let current = LANGUAGES
+        .iter()
+        .position(|language| *language == settings.language)
+        .unwrap_or(0);
+    settings.language = String::from(LANGUAGES[(current + 1) % LANGUAGES.len()]);
+
+    commands.spawn((
+        widget::ui_center_root("Language"),
+        GlobalZIndex(2),
+        LanguageText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(widget::large_text(""), Localized::new("language"))],
+    ));
+}
+
+fn update_localization(
+    mut asset_events: EventReader<AssetEvent<StringTable>>,
+    settings: Res<Settings>,
+    handles: Res<StringTableHandles>,
+    tables: Res<Assets<StringTable>>,
+    asset_server: Res<AssetServer>,
+    mut localization: ResMut<Localization>,
+) {
+    if asset_events.read().count() == 0 && !settings.is_changed() {
+        return;
+    }
+
+    let table = LANGUAGES
+        .iter()
+        .position(|language| *language == settings.language)
+        .and_then(|index| tables.get(&handles.0[index]));
+    localization.current = table.cloned();
+    localization.font = table
+        .and_then(|table| table.font.as_ref())
+        .map(|font| asset_server.load(font))
+        .unwrap_or_default();
+}
+
+fn localize_text(
+    localization: Res<Localization>,
+    mut text_query: Query<(Ref<Localized>, &mut Text, &mut TextFont)>,
+) {
+    for (localized, mut text, mut font) in text_query.iter_mut() {
+        if localization.is_changed() || localized.is_changed() {
+            text.0 = localization.get(&localized);
+            font.font = localization.font();
+        }
+    }
+}
+
+fn localize_text_2d(
+    localization: Res<Localization>,
+    mut text_query: Query<(Ref<Localized>, &mut Text2d, &mut TextFont)>,
+) {
+    for (localized, mut text, mut font) in text_query.iter_mut() {
+        if localization.is_changed() || localized.is_changed() {
+            text.0 = localization.get(&localized);
+            font.font = localization.font();
+        }
+    }
+}
+
+fn update_window_title(
+    localization: Res<Localization>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !localization.is_changed() {
+        return;
+    }
+    for mut window in window_query.iter_mut() {
+        window.title = localization.get(&Localized::new("title"));
+    }
+}
+
+fn destroy_language_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut LanguageText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plural_categories() {
+        use PluralCategory::*;
+        let categories = |rule: PluralRule, counts: &[i64]| {
+            counts
+                .iter()
+                .map(|count| rule.category(*count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            categories(PluralRule::OneOther, &[0, 1, 2, -1, 21]),
+            [Other, One, Other, One, Other]
+        );
+        assert_eq!(
+            categories(PluralRule::ZeroOrOne, &[0, 1, 2, -1, 21]),
+            [One, One, Other, One, Other]
+        );
+        assert_eq!(
+            categories(PluralRule::OtherOnly, &[0, 1, 2, 5]),
+            [Other, Other, Other, Other]
+        );
+        assert_eq!(
+            categories(
+                PluralRule::EastSlavic,
+                &[1, 21, 101, 2, 4, 23, 0, 5, 11, 12, 14, 111, 112, 25]
+            ),
+            [
+                One, One, One, Few, Few, Few, Many, Many, Many, Many, Many, Many, Many, Many
+            ]
+        );
+    }
+
+    fn localization(current: &str) -> Localization {
+        Localization {
+            current: Some(ron::from_str(current).unwrap()),
+            ..default()
+        }
+    }
+
+    const FRENCH: &str = r#"(
+        plural_rule: ZeroOrOne,
+        strings: { "level": "Niveau {level} (Max : {max})" },
+        plurals: { "lives": (one: Some("{count} vie"), other: "{count} vies") },
+    )"#;
+
+    #[test]
+    fn get_fills_in_the_current_table() {
+        let localization = localization(FRENCH);
+        let level = Localized::new("level").arg("level", 3).arg("max", 7);
+        assert_eq!(localization.get(&level), "Niveau 3 (Max : 7)");
+        assert_eq!(localization.get(&Localized::new("lives").count(0)), "0 vie");
+        assert_eq!(
+            localization.get(&Localized::new("lives").count(2)),
+            "2 vies"
+        );
+    }
+
+    #[test]
+    fn missing_keys_fall_back() {
+        let localization = localization(FRENCH);
+        //to English, then to the text's own fallback, then to the key itself
+        assert_eq!(
+            localization.get(&Localized::new("title")),
+            localization.english.strings["title"]
+        );
+        assert_eq!(
+            localization.get(&Localized::new("no_such_key").or("Boss wave")),
+            "Boss wave"
+        );
+        assert_eq!(
+            localization.get(&Localized::new("no_such_key")),
+            "no_such_key"
+        );
+        //a key that is only a plural is not found without a count
+        assert_eq!(localization.get(&Localized::new("lives")), "lives");
+    }
+
+    #[test]
+    fn missing_arguments_are_left_as_placeholders() {
+        let localization = localization(FRENCH);
+        assert_eq!(
+            localization.get(&Localized::new("level").arg("level", 3)),
+            "Niveau 3 (Max : {max})"
+        );
+        //and arguments nothing asks for are ignored
+        assert_eq!(
+            localization.get(
+                &Localized::new("level")
+                    .arg("level", 3)
+                    .arg("max", 4)
+                    .arg("x", 5)
+            ),
+            "Niveau 3 (Max : 4)"
+        );
+    }
+
+    #[test]
+    fn shipped_tables_load() {
+        for table in [
+            BUILT_IN_ENGLISH,
+            include_str!("../assets/locale/fr.strings.ron"),
+        ] {
+            assert!(ron::from_str::<StringTable>(table).is_ok());
+        }
+    }
+}
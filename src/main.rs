@@ -1,23 +1,13 @@
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
 use bevy_rustysynth::RustySynthPlugin;
-
-pub mod alien;
-pub mod alien_layouts;
-pub mod alien_projectile;
-pub mod capsule;
-pub mod fire;
-pub mod game;
-pub mod game_audio;
-pub mod level_indicator;
-pub mod player;
-pub mod projectile;
-pub mod resolution;
-pub mod star_field;
-pub mod widget;
+use chain_reaxian::{game, resolution, settings};
 
 fn main() {
+    let settings = settings::Settings::load();
+
     App::new()
+        .insert_resource(settings)
         .add_plugins((
             //list of plugins added to the game
             DefaultPlugins
@@ -30,9 +20,10 @@ fn main() {
                 })
                 .set(WindowPlugin {
                     primary_window: Some(Window {
+                        //replaced by the title in the player's language, see `localization`
                         title: String::from("Chain Reaxian"),
                         position: WindowPosition::Centered(MonitorSelection::Primary),
-                        resolution: Vec2::new(612., 612.).into(),
+                        resolution: resolution::WINDOW_SIZE.into(),
                         ..Default::default()
                     }),
                     ..Default::default()
@@ -0,0 +1,285 @@
+//! Helpers for rewriting Standard MIDI Files before they are handed to the synthesizer.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::asset::io::embedded::EmbeddedAssetRegistry;
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use bevy_rustysynth::MidiAudio;
+
+pub struct MidiSourcePlugin;
+
+impl Plugin for MidiSourcePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<MidiSource>()
+            .register_asset_loader(MidiSourceLoader);
+    }
+}
+
+//the bytes of a midi file. `MidiAudio` keeps its bytes to itself, so a file that needs rewriting is
+//also loaded as one of these
+#[derive(Asset, TypePath)]
+pub struct MidiSource(pub Vec<u8>);
+
+struct MidiSourceLoader;
+
+impl AssetLoader for MidiSourceLoader {
+    type Asset = MidiSource;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<MidiSource, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(MidiSource(bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mid", "midi"]
+    }
+}
+
+//`MidiAudio` can only be made by loading a file, so midi made in game is written to the in-memory
+//`embedded://` asset source and loaded from there. `name` must be unique to the contents.
+pub fn load_generated(
+    asset_server: &AssetServer,
+    registry: &EmbeddedAssetRegistry,
+    name: &str,
+    smf: Vec<u8>,
+) -> Handle<MidiAudio> {
+    let path = PathBuf::from(format!("chain_reaxian/music/{name}.mid"));
+    registry.insert_asset(path.clone(), &path, smf);
+    asset_server.load(format!("embedded://chain_reaxian/music/{name}.mid"))
+}
+
+//ticks per quarter note of the files we write; at the default tempo of 120 bpm that is 960 a second
+const DIVISION: u16 = 480;
+const TICKS_PER_SECOND: f32 = 960.;
+
+//a file that plays `keys` one after another on the instrument `preset`
+pub fn note_sequence(preset: u8, keys: &[u8], velocity: u8, note_length: Duration) -> Vec<u8> {
+    let ticks = (note_length.as_secs_f32() * TICKS_PER_SECOND) as u32;
+    let mut track = vec![0x00, 0xC0, preset];
+    for key in keys {
+        write_vlq(&mut track, 0);
+        track.extend_from_slice(&[0x90, *key, velocity]);
+        write_vlq(&mut track, ticks);
+        track.extend_from_slice(&[0x80, *key, 0]);
+    }
+    //end of track
+    track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+    let mut smf = b"MThd".to_vec();
+    smf.extend_from_slice(&6u32.to_be_bytes());
+    //format 0 with a single track
+    smf.extend_from_slice(&0u16.to_be_bytes());
+    smf.extend_from_slice(&1u16.to_be_bytes());
+    smf.extend_from_slice(&DIVISION.to_be_bytes());
+    smf.extend_from_slice(b"MTrk");
+    smf.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    smf.extend_from_slice(&track);
+    smf
+}
+
+//keeps the header, tempo and other meta events, but only the channel events of `channels`.
+//every track keeps its original length so that the resulting files loop in step with each other.
+pub fn filter_channels(smf: &[u8], channels: &[u8]) -> Option<Vec<u8>> {
+    if smf.len() < 14 || &smf[0..4] != b"MThd" {
+        return None;
+    }
+    let header_len = read_u32(smf, 4)? as usize;
+    let mut pos = 8 + header_len;
+    let mut out = smf.get(0..pos)?.to_vec();
+
+    while pos + 8 <= smf.len() {
+        let chunk_len = read_u32(smf, pos + 4)? as usize;
+        let chunk_end = pos + 8 + chunk_len;
+        let chunk = smf.get(pos + 8..chunk_end)?;
+        if &smf[pos..pos + 4] == b"MTrk" {
+            let track = filter_track(chunk, channels)?;
+            out.extend_from_slice(b"MTrk");
+            out.extend_from_slice(&(track.len() as u32).to_be_bytes());
+            out.extend_from_slice(&track);
+        } else {
+            //unknown chunks are passed through untouched
+            out.extend_from_slice(&smf[pos..chunk_end]);
+        }
+        pos = chunk_end;
+    }
+
+    Some(out)
+}
+
+//the largest number a variable length quantity can hold
+const MAX_DELTA: u32 = 0x0FFF_FFFF;
+
+fn filter_track(track: &[u8], channels: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(track.len());
+    let mut pos = 0;
+    let mut running_status = 0u8;
+    //ticks since the last event we kept
+    let mut pending_delta = 0u32;
+
+    while pos < track.len() {
+        let (delta, next) = read_vlq(track, pos)?;
+        pos = next;
+        //a delta has to fit in the four bytes a midi file allows for it
+        pending_delta = pending_delta
+            .checked_add(delta)
+            .filter(|delta| *delta <= MAX_DELTA)?;
+
+        let status = *track.get(pos)?;
+        if status == 0xFF || status == 0xF0 || status == 0xF7 {
+            //meta events carry a type byte before their length, sysex events do not
+            let len_pos = if status == 0xFF { pos + 2 } else { pos + 1 };
+            let (len, data) = read_vlq(track, len_pos)?;
+            let event = track.get(pos..data + len as usize)?;
+            pos = data + len as usize;
+
+            write_vlq(&mut out, pending_delta);
+            pending_delta = 0;
+            out.extend_from_slice(event);
+            continue;
+        }
+
+        if status & 0x80 != 0 {
+            running_status = status;
+            pos += 1;
+        }
+        let data_len = match running_status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            0x80 | 0x90 | 0xA0 | 0xB0 | 0xE0 => 2,
+            _ => return None,
+        };
+        let data = track.get(pos..pos + data_len)?;
+        pos += data_len;
+
+        if channels.contains(&(running_status & 0x0F)) {
+            //always write the status byte, since the previous event may have been dropped
+            write_vlq(&mut out, pending_delta);
+            pending_delta = 0;
+            out.push(running_status);
+            out.extend_from_slice(data);
+        }
+    }
+
+    Some(out)
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
+
+//the most bytes a variable length quantity can take, enough for `MAX_DELTA`
+const MAX_VLQ_LEN: usize = 4;
+
+fn read_vlq(bytes: &[u8], pos: usize) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (len, byte) in bytes.get(pos..)?.iter().take(MAX_VLQ_LEN).enumerate() {
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Some((value, pos + len + 1));
+        }
+    }
+    None
+}
+
+fn write_vlq(out: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlq_round_trips() {
+        for value in [0, 0x7F, 0x80, 0x3FFF, 0x4000, 0x1F_FFFF, MAX_DELTA] {
+            let mut bytes = vec![0xAA];
+            write_vlq(&mut bytes, value);
+            assert_eq!(
+                read_vlq(&bytes, 1),
+                Some((value, bytes.len())),
+                "{value:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn vlq_longer_than_four_bytes_is_rejected() {
+        assert_eq!(read_vlq(&[0xFF, 0xFF, 0xFF, 0x7F], 0), Some((MAX_DELTA, 4)));
+        assert_eq!(read_vlq(&[0x81, 0x80, 0x80, 0x80, 0x00], 0), None);
+        //nor may one run off the end
+        assert_eq!(read_vlq(&[0x81, 0x80], 0), None);
+        assert_eq!(read_vlq(&[0x00], 1), None);
+    }
+
+    //a format 0 file whose one track plays a note on channel 0 and then one on channel 1
+    fn two_channel_smf() -> Vec<u8> {
+        let track = [
+            0x00, 0x90, 60, 100, // note on, channel 0
+            0x10, 0x80, 60, 0, // note off, channel 0
+            0x10, 0x91, 64, 100, // note on, channel 1
+            0x10, 0x81, 64, 0, // note off, channel 1
+            0x00, 0xFF, 0x2F, 0x00, // end of track
+        ];
+        let mut smf = b"MThd".to_vec();
+        smf.extend_from_slice(&6u32.to_be_bytes());
+        smf.extend_from_slice(&[0, 0, 0, 1]);
+        smf.extend_from_slice(&DIVISION.to_be_bytes());
+        smf.extend_from_slice(b"MTrk");
+        smf.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        smf.extend_from_slice(&track);
+        smf
+    }
+
+    fn track(smf: &[u8]) -> &[u8] {
+        &smf[22..]
+    }
+
+    #[test]
+    fn filter_keeps_only_the_channels_asked_for() {
+        let smf = two_channel_smf();
+        let first = filter_channels(&smf, &[0]).unwrap();
+        assert_eq!(
+            track(&first),
+            [
+                0x00, 0x90, 60, 100, 0x10, 0x80, 60, 0, 0x20, 0xFF, 0x2F, 0x00
+            ]
+        );
+        //the dropped note's time carries over to the next kept event, so the layers stay in step
+        let second = filter_channels(&smf, &[1]).unwrap();
+        assert_eq!(
+            track(&second),
+            [
+                0x20, 0x91, 64, 100, 0x10, 0x81, 64, 0, 0x00, 0xFF, 0x2F, 0x00
+            ]
+        );
+        assert_eq!(filter_channels(&smf, &[0, 1]).unwrap(), smf);
+    }
+
+    #[test]
+    fn filter_rejects_broken_files() {
+        let smf = two_channel_smf();
+        assert_eq!(filter_channels(b"RIFF0000000000", &[0]), None);
+        assert_eq!(filter_channels(&smf[..smf.len() - 2], &[0]), None);
+        //a delta that runs past four bytes
+        let mut long_delta = smf.clone();
+        long_delta[22..26].copy_from_slice(&[0x80, 0x80, 0x80, 0x80]);
+        assert_eq!(filter_channels(&long_delta, &[0]), None);
+    }
+}
@@ -0,0 +1,336 @@
+//! Picks the background music for the current level from the soundtracks described in
+//! `sounds/soundtracks.music.ron`, crossfading whenever the level calls for a different track.
+//!
+//! Press M to switch soundtrack. The choice is saved in [`Settings`]. A soundtrack can name its own
+//! soundfont, which the synthesizer switches to before the soundtrack's music starts.
+
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use bevy_rustysynth::{RustySynthSet, SetSoundfontEvent};
+use rustysynth::{SoundFont, SoundFontError};
+use serde::Deserialize;
+
+use crate::{
+    adaptive_music::{MusicLayer, MusicTrack},
+    level_indicator::ScoreManager,
+    localization::Localized,
+    ron_asset::RonAssetPlugin,
+    settings::Settings,
+    widget,
+};
+
+pub struct MusicPlaylistPlugin;
+
+impl Plugin for MusicPlaylistPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<Soundtracks>::new(&["music.ron"]))
+            .init_asset::<Soundfont>()
+            .register_asset_loader(SoundfontLoader)
+            .add_systems(Startup, setup_playlist)
+            .add_systems(
+                Update,
+                (
+                    cycle_soundtrack,
+                    apply_soundfont.before(RustySynthSet::Update),
+                    choose_track,
+                    destroy_soundtrack_text,
+                )
+                    .chain(),
+            );
+    }
+}
+
+const SOUNDTRACKS_PATH: &str = "sounds/soundtracks.music.ron";
+
+#[derive(Deserialize)]
+pub struct TrackDefinition {
+    pub midi: String,
+    //the track plays from `first_level` up to and including `last_level`
+    #[serde(default)]
+    pub first_level: i32,
+    #[serde(default)]
+    pub last_level: Option<i32>,
+    //boss tracks play instead of the regular ones on every boss wave
+    #[serde(default)]
+    pub boss: bool,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub layers: Vec<MusicLayer>,
+}
+
+fn default_volume() -> f32 {
+    1.
+}
+
+fn default_speed() -> f32 {
+    1.
+}
+
+impl TrackDefinition {
+    fn plays_on(&self, level: i32) -> bool {
+        level >= self.first_level && self.last_level.is_none_or(|last| level <= last)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Soundtrack {
+    pub name: String,
+    //a .sf2 file in the assets folder to synthesize this soundtrack with
+    #[serde(default)]
+    pub soundfont: Option<String>,
+    //every `boss_every`th level is a boss wave
+    #[serde(default)]
+    pub boss_every: Option<i32>,
+    pub tracks: Vec<TrackDefinition>,
+}
+
+impl Soundtrack {
+    fn track_for_level(&self, level: i32) -> Option<usize> {
+        let boss = self
+            .boss_every
+            .is_some_and(|every| every > 0 && level % every == 0);
+        let find = |boss: bool| {
+            self.tracks
+                .iter()
+                .position(|track| track.boss == boss && track.plays_on(level))
+        };
+
+        //fall back to the regular music when there is no boss track for this level
+        find(boss).or_else(|| find(false))
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Soundtracks {
+    pub soundtracks: Vec<Soundtrack>,
+}
+
+impl Soundtracks {
+    fn find(&self, name: &str) -> Option<&Soundtrack> {
+        self.soundtracks
+            .iter()
+            .find(|soundtrack| soundtrack.name == name)
+            .or(self.soundtracks.first())
+    }
+}
+
+//the bytes of a .sf2 file the synthesizer is able to read
+#[derive(Asset, TypePath)]
+pub struct Soundfont(pub Vec<u8>);
+
+struct SoundfontLoader;
+
+impl AssetLoader for SoundfontLoader {
+    type Asset = Soundfont;
+    type Settings = ();
+    type Error = SoundFontError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Soundfont, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        //the synthesizer panics on a soundfont it can't read, so a bad file fails to load instead
+        SoundFont::new(&mut bytes.as_slice())?;
+        Ok(Soundfont(bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sf2"]
+    }
+}
+
+#[derive(Resource)]
+struct Playlist {
+    soundtracks: Handle<Soundtracks>,
+    //the soundtrack name and track index that is playing
+    playing: Option<(String, usize)>,
+    //the soundfont the synthesizer uses, `None` for the one it comes with
+    soundfont: Option<String>,
+    //set while that soundfont is loading, and no new music starts until it has
+    loading_soundfont: Option<Handle<Soundfont>>,
+}
+
+#[derive(Component)]
+struct SoundtrackText {
+    time_remaining: f32,
+}
+
+const TEXT_TIME: f32 = 2.5;
+
+fn setup_playlist(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Playlist {
+        soundtracks: asset_server.load(SOUNDTRACKS_PATH),
+        playing: None,
+        soundfont: None,
+        loading_soundfont: None,
+    });
+}
+
+//tracks are synthesized with the soundfont that is set when they start, so the soundfont is
+//switched before the chosen soundtrack's next track starts
+fn apply_soundfont(
+    asset_server: Res<AssetServer>,
+    mut playlist: ResMut<Playlist>,
+    soundtracks: Res<Assets<Soundtracks>>,
+    soundfonts: Res<Assets<Soundfont>>,
+    settings: Res<Settings>,
+    mut soundfont_events: EventWriter<SetSoundfontEvent>,
+) {
+    if let Some(handle) = &playlist.loading_soundfont {
+        if let Some(Soundfont(bytes)) = soundfonts.get(handle) {
+            soundfont_events.write(SetSoundfontEvent::Bytes(bytes.clone()));
+        } else if asset_server.load_state(handle).is_failed() {
+            warn!(
+                "could not load soundfont {}, keeping the default one",
+                playlist.soundfont.as_deref().unwrap_or_default()
+            );
+            soundfont_events.write(SetSoundfontEvent::Default);
+        } else {
+            return;
+        }
+        playlist.loading_soundfont = None;
+        return;
+    }
+
+    let Some(soundtrack) = soundtracks
+        .get(&playlist.soundtracks)
+        .and_then(|soundtracks| soundtracks.find(&settings.soundtrack))
+    else {
+        return;
+    };
+    if soundtrack.soundfont == playlist.soundfont {
+        return;
+    }
+
+    playlist.soundfont = soundtrack.soundfont.clone();
+    match &soundtrack.soundfont {
+        Some(path) => playlist.loading_soundfont = Some(asset_server.load(path.clone())),
+        None => {
+            soundfont_events.write(SetSoundfontEvent::Default);
+        }
+    }
+}
+
+fn choose_track(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut playlist: ResMut<Playlist>,
+    soundtracks: Res<Assets<Soundtracks>>,
+    settings: Res<Settings>,
+    score_manager: Res<ScoreManager>,
+    mut track_query: Query<&mut MusicTrack>,
+) {
+    if playlist.loading_soundfont.is_some() {
+        return;
+    }
+    let Some(soundtrack) = soundtracks
+        .get(&playlist.soundtracks)
+        .and_then(|soundtracks| soundtracks.find(&settings.soundtrack))
+    else {
+        return;
+    };
+    let Some(index) = soundtrack.track_for_level(score_manager.curr_level) else {
+        return;
+    };
+    if playlist
+        .playing
+        .as_ref()
+        .is_some_and(|(name, playing)| *name == soundtrack.name && *playing == index)
+    {
+        return;
+    }
+
+    for mut track in track_query.iter_mut() {
+        track.fade_target = 0.;
+    }
+
+    let definition = &soundtrack.tracks[index];
+    commands.spawn((
+        Name::new("Music"),
+        MusicTrack::new(
+            &asset_server,
+            &definition.midi,
+            definition.layers.clone(),
+            definition.volume,
+            definition.speed,
+        ),
+    ));
+    playlist.playing = Some((soundtrack.name.clone(), index));
+}
+
+fn cycle_soundtrack(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    playlist: Res<Playlist>,
+    soundtracks: Res<Assets<Soundtracks>>,
+    mut settings: ResMut<Settings>,
+) {
+    if !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+    let Some(soundtracks) = soundtracks.get(&playlist.soundtracks) else {
+        return;
+    };
+    if soundtracks.soundtracks.is_empty() {
+        return;
+    }
+
+    let current = soundtracks
+        .soundtracks
+        .iter()
+        .position(|soundtrack| soundtrack.name == settings.soundtrack)
+        .unwrap_or(0);
+    let next = &soundtracks.soundtracks[(current + 1) % soundtracks.soundtracks.len()];
+    settings.soundtrack = next.name.clone();
+
+    commands.spawn((
+        widget::ui_center_root("Soundtrack"),
+        GlobalZIndex(2),
+        SoundtrackText {
+            time_remaining: TEXT_TIME,
+        },
+        children![(
+            widget::large_text(""),
+            Localized::new("soundtrack").arg("name", &next.name)
+        )],
+    ));
+}
+
+fn destroy_soundtrack_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut SoundtrackText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soundtrack_soundfonts_load() {
+        let soundtracks: Soundtracks =
+            ron::de::from_str(include_str!("../assets/sounds/soundtracks.music.ron")).unwrap();
+        for path in soundtracks
+            .soundtracks
+            .iter()
+            .filter_map(|soundtrack| soundtrack.soundfont.as_ref())
+        {
+            let bytes = std::fs::read(format!("assets/{path}")).unwrap();
+            assert!(SoundFont::new(&mut bytes.as_slice()).is_ok(), "{path}");
+        }
+    }
+}
@@ -1,11 +1,22 @@
+use std::sync::Arc;
+
 use bevy::prelude::*;
 
-use crate::alien_projectile::PlayerKilledEvent;
+use crate::alien_projectile::{PlayerHitEvent, PlayerKilledEvent};
 use crate::capsule::CapsuleCollisionEvent;
+use crate::collider::{Collider, Layers};
+use crate::console::ConsoleAppExt;
+use crate::difficulty::Difficulty;
+use crate::game_mode::GameMode;
 use crate::level_indicator::LevelCompletedEvent;
 use crate::level_indicator::ScoreManager;
-use crate::projectile;
+use crate::pool::{Pool, Textures};
+use crate::projectile::{self, AlienKilledEvent};
 use crate::resolution;
+use crate::settings::Settings;
+use crate::title_screen::Screen;
+use crate::ufo::UfoKilledEvent;
+use crate::weapon::{self, EquippedWeapon, Shot, Weapon};
 
 pub struct PlayerPlugin;
 
@@ -13,221 +24,430 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_player).add_systems(
             Update,
-            (update_player, capsule_collision, reset_when_killed),
+            (
+                read_controls
+                    .in_set(PlayerInputSystems)
+                    .run_if(in_state(Screen::Playing)),
+                update_player.after(PlayerInputSystems),
+                capsule_collision,
+                award_points,
+                player_hit,
+                reset_when_killed,
+            ),
         );
         app.add_event::<PlayerShootEvent>();
+        app.add_console_command("god <on|off>", god_mode)
+            .add_console_command("weapon <name>", equip_weapon);
+    }
+}
+
+//a cheat for the console and the debug panel: makes every player invincible, or takes it away
+pub fn set_invincible(world: &mut World, on: bool) {
+    let players: Vec<Entity> = world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(world)
+        .collect();
+    for entity in players {
+        if on {
+            world.entity_mut(entity).insert(Invincible);
+        } else {
+            world.entity_mut(entity).remove::<Invincible>();
+        }
+    }
+}
+
+fn god_mode(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let on = match args.first() {
+        Some(&"on") => true,
+        Some(&"off") => false,
+        _ => return Err(String::from("usage: god <on|off>")),
+    };
+    set_invincible(world, on);
+    Ok(format!("god mode {}", if on { "on" } else { "off" }))
+}
+
+//gives every player the named weapon
+fn equip_weapon(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let name = args
+        .first()
+        .ok_or_else(|| String::from("usage: weapon <name>"))?;
+    if weapon::by_name(name).is_none() {
+        return Err(format!(
+            "no weapon called {name}, try one of {}",
+            weapon::NAMES.join(", ")
+        ));
+    }
+    let mut weapon_query = world.query::<&mut EquippedWeapon>();
+    for mut equipped in weapon_query.iter_mut(world) {
+        equipped.0 = weapon::by_name(name).unwrap();
     }
+    Ok(format!("equipped {name}"))
 }
 
 #[derive(Component)]
 pub struct Player {
+    //which player this is, 0 for the first
+    pub slot: usize,
     //provides cooldown for shooting so we don't just shoot a bullet every frame
     pub shoot_timer: f32,
+    //out of lives until the run starts over
     pub dead: bool,
+    pub lives: i32,
+    pub score: i32,
     pub main_gun_projectiles: i32,
     pub side_gun_projectiles: i32,
 }
 
+impl Player {
+    //each capsule adds a side gun and a main gun in turn, until the side guns are maxed out
+    pub fn upgrade_guns(&mut self) {
+        if self.side_gun_projectiles < MAX_SIDE_BULLETS {
+            if self.main_gun_projectiles > self.side_gun_projectiles {
+                self.side_gun_projectiles += 1;
+            } else {
+                self.main_gun_projectiles += 1;
+            }
+        }
+    }
+
+    //how many upgrades the guns have had, up to `MAX_GUN_LEVEL`
+    pub fn gun_level(&self) -> i32 {
+        self.main_gun_projectiles + self.side_gun_projectiles - 1
+    }
+
+    pub fn set_gun_level(&mut self, level: i32) {
+        self.main_gun_projectiles = 1;
+        self.side_gun_projectiles = 0;
+        for _ in 0..level {
+            self.upgrade_guns();
+        }
+    }
+}
+
+//a cheat for testing: alien projectiles pass through this player
+#[derive(Component)]
+pub struct Invincible;
+
+//what a player wants to do this frame, filled in from their controls or by the autopilot
+#[derive(Component, Default)]
+pub struct PlayerInput {
+    //-1 for full speed left up to 1 for full speed right
+    pub horizontal: f32,
+    pub fire: bool,
+}
+
+//systems that fill in `PlayerInput` run in this set, before the ships move
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSystems;
+
+//a player in alternating mode who is waiting for the other player's turn to end
+#[derive(Component)]
+pub struct AwaitingTurn;
+
+struct Controls {
+    left: KeyCode,
+    right: KeyCode,
+    fire: KeyCode,
+}
+
+//each player also uses the gamepad with the same index, in the order they were connected
+const CONTROLS: [Controls; 2] = [
+    Controls {
+        left: KeyCode::KeyA,
+        right: KeyCode::KeyD,
+        fire: KeyCode::Space,
+    },
+    Controls {
+        left: KeyCode::ArrowLeft,
+        right: KeyCode::ArrowRight,
+        fire: KeyCode::Enter,
+    },
+];
+
+//the second ship is tinted so the players can tell each other apart
+const PLAYER_COLORS: [Color; 2] = [Color::WHITE, Color::srgb(0.6, 0.9, 1.0)];
+const PLAYER_SPACING: f32 = 120.;
+const STICK_DEAD_ZONE: f32 = 0.3;
+
 fn setup_player(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     resolution: Res<resolution::Resolution>,
+    settings: Res<Settings>,
+) {
+    spawn_players(&mut commands, &asset_server, &resolution, settings.mode);
+}
+
+pub fn spawn_players(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    resolution: &resolution::Resolution,
+    mode: GameMode,
 ) {
     let player_sheet = asset_server.load("images/player.png");
-    commands.spawn((
-        Sprite {
-            image: player_sheet,
-            ..Default::default()
-        },
-        Transform::from_xyz(
-            0.,
-            -(resolution.screen_dimensions.y * 0.5) + (resolution.pixel_ratio * 25.0),
-            0.,
-        )
-        .with_scale(Vec3::splat(resolution.pixel_ratio)),
-        Player {
-            shoot_timer: 0.,
-            dead: false,
-            main_gun_projectiles: 1,
-            side_gun_projectiles: 0,
-        },
-    ));
+    let num_players = mode.players();
+    for (slot, color) in PLAYER_COLORS.into_iter().enumerate().take(num_players) {
+        let x = if mode.takes_turns() {
+            0.
+        } else {
+            (slot as f32 - (num_players - 1) as f32 * 0.5) * PLAYER_SPACING
+        };
+        let mut player = commands.spawn((
+            Sprite {
+                image: player_sheet.clone(),
+                color,
+                ..Default::default()
+            },
+            Transform::from_xyz(
+                x,
+                -(resolution.screen_dimensions.y * 0.5) + (resolution.pixel_ratio * 25.0),
+                0.,
+            )
+            .with_scale(Vec3::splat(resolution.pixel_ratio)),
+            Player {
+                slot,
+                shoot_timer: 0.,
+                dead: false,
+                lives: mode.lives(),
+                score: 0,
+                main_gun_projectiles: 1,
+                side_gun_projectiles: 0,
+            },
+            PlayerInput::default(),
+            EquippedWeapon::default(),
+            COLLIDER,
+        ));
+        if mode.takes_turns() && slot > 0 {
+            player.insert((AwaitingTurn, Visibility::Hidden));
+        }
+    }
 }
 
+//just the cockpit, so shots only hit when they look like they do
+pub const COLLIDER: Collider = Collider::rect(8., 8.).on(Layers::PLAYER);
 const SPEED: f32 = 200.;
-const BULLET_SPEED: f32 = 400.;
-const SHOOT_COOLDOWN: f32 = 0.9;
+pub const BULLET_SPEED: f32 = 400.;
+pub const SHOOT_COOLDOWN: f32 = 0.9;
 
 #[derive(Event, Debug)]
 pub struct PlayerShootEvent {}
 
+//reads each player's controls into their `PlayerInput`
+fn read_controls(
+    mut player_query: Query<(&Player, &mut PlayerInput)>,
+    gamepad_query: Query<(Entity, &Gamepad)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<Settings>,
+) {
+    let mut gamepads: Vec<(Entity, &Gamepad)> = gamepad_query.iter().collect();
+    gamepads.sort_by_key(|(entity, _)| *entity);
+
+    for (player, mut input) in player_query.iter_mut() {
+        let controls = &CONTROLS[player.slot];
+        let gamepad = gamepads.get(player.slot).map(|(_, gamepad)| *gamepad);
+
+        //the input which the player is pressing for the horizontal axis
+        let mut horizontal = 0.;
+
+        if keys.pressed(controls.left) {
+            horizontal += -1.;
+        }
+        if keys.pressed(controls.right) {
+            horizontal += 1.;
+        }
+        if let Some(gamepad) = gamepad {
+            let stick = gamepad.left_stick().x + gamepad.dpad().x;
+            if stick.abs() > STICK_DEAD_ZONE {
+                horizontal += stick.clamp(-1., 1.);
+            }
+        }
+
+        input.horizontal = horizontal;
+        input.fire = settings.accessibility.auto_fire
+            || keys.pressed(controls.fire)
+            || gamepad.is_some_and(|gamepad| gamepad.pressed(GamepadButton::South));
+    }
+}
+
 fn update_player(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut player_query: Query<(&mut Player, &mut Transform)>,
+    textures: Res<Textures>,
+    mut pool: ResMut<Pool<projectile::Projectile>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Player,
+            &PlayerInput,
+            &EquippedWeapon,
+            &mut Transform,
+        ),
+        Without<AwaitingTurn>,
+    >,
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
     resolution: Res<resolution::Resolution>,
+    difficulty: Res<Difficulty>,
     mut events: EventWriter<PlayerShootEvent>,
 ) {
-    let (mut player, mut transform) = player_query.single_mut().unwrap();
-
-    //the input which the player is pressing for the horizontal axis
-    let mut horizontal = 0.;
+    for (entity, mut player, input, weapon, mut transform) in player_query.iter_mut() {
+        if player.dead {
+            continue;
+        }
 
-    if keys.pressed(KeyCode::KeyA) {
-        horizontal += -1.;
-    }
-    if keys.pressed(KeyCode::KeyD) {
-        horizontal += 1.;
-    }
-    //move player
-    transform.translation.x += horizontal * time.delta_secs() * SPEED;
+        //move player
+        transform.translation.x += input.horizontal.clamp(-1., 1.) * time.delta_secs() * SPEED;
 
-    //confine player
-    let left_bound = -resolution.screen_dimensions.x * 0.5;
-    let right_bound = resolution.screen_dimensions.x * 0.5;
+        //confine player
+        let left_bound = -resolution.screen_dimensions.x * 0.5;
+        let right_bound = resolution.screen_dimensions.x * 0.5;
 
-    if transform.translation.x > right_bound {
-        transform.translation.x = right_bound;
-    }
-    if transform.translation.x < left_bound {
-        transform.translation.x = left_bound;
-    }
+        if transform.translation.x > right_bound {
+            transform.translation.x = right_bound;
+        }
+        if transform.translation.x < left_bound {
+            transform.translation.x = left_bound;
+        }
 
-    player.shoot_timer -= time.delta_secs();
+        player.shoot_timer -= time.delta_secs();
 
-    if keys.pressed(KeyCode::Space) && player.shoot_timer <= 0. {
-        events.write(PlayerShootEvent {});
-        player.shoot_timer = SHOOT_COOLDOWN;
-        spawn_one_missile(
-            &mut commands,
-            &asset_server,
-            &resolution,
-            &transform,
-            player.main_gun_projectiles,
-        );
-        spawn_two_missiles(
-            &mut commands,
-            &asset_server,
-            &resolution,
-            &transform,
-            player.side_gun_projectiles,
-        );
+        if input.fire && player.shoot_timer <= 0. {
+            events.write(PlayerShootEvent {});
+            player.shoot_timer = weapon.0.cooldown(&difficulty);
+            spawn_shots(
+                &mut commands,
+                &mut pool,
+                &textures,
+                &resolution,
+                entity,
+                &transform,
+                &weapon.0,
+                weapon.0.shots(&player),
+                weapon.0.speed(&difficulty),
+            );
+        }
     }
 }
 
 const MAX_SIDE_BULLETS: i32 = 6;
+pub const MAX_GUN_LEVEL: i32 = MAX_SIDE_BULLETS * 2 - 1;
 
 fn capsule_collision(
     mut capsule_collision_events: EventReader<CapsuleCollisionEvent>,
-    mut player_query: Query<&mut Player>,
+    mut player_query: Query<(&mut Player, &mut EquippedWeapon)>,
     mut level_completed_events: EventWriter<LevelCompletedEvent>,
 ) {
-    let mut player = player_query.single_mut().unwrap();
-    for _ in capsule_collision_events.read() {
-        if player.side_gun_projectiles < MAX_SIDE_BULLETS {
-            if player.main_gun_projectiles > player.side_gun_projectiles {
-                player.side_gun_projectiles += 1;
-            } else {
-                player.main_gun_projectiles += 1;
-            }
+    for event in capsule_collision_events.read() {
+        //the capsule upgrades the guns of whoever caught it, and swaps them for the weapon it
+        //carries, if any
+        let Ok((mut player, mut equipped)) = player_query.get_mut(event.player) else {
+            continue;
+        };
+        player.upgrade_guns();
+        if let Some(weapon) = event.weapon.and_then(weapon::by_name) {
+            equipped.0 = weapon;
         }
 
         level_completed_events.write(LevelCompletedEvent {});
     }
 }
 
-const PRIMARY_GUN_HEIGHT: f32 = 25.0;
-const BULLET_HEIGHT: f32 = 12.0;
-
-fn spawn_one_missile(
+fn spawn_shots(
     commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    resolution: &Res<resolution::Resolution>,
-    transform: &Mut<'_, Transform>,
-    num_missiles: i32,
+    pool: &mut Pool<projectile::Projectile>,
+    textures: &Textures,
+    resolution: &resolution::Resolution,
+    player: Entity,
+    transform: &Transform,
+    weapon: &Arc<dyn Weapon>,
+    shots: Vec<Shot>,
+    speed: f32,
 ) {
-    let bullet_texture: Handle<Image> = asset_server.load("images/chain.png");
-    let mut y_pos = transform.translation.y + PRIMARY_GUN_HEIGHT;
-
-    for _ in 0..num_missiles {
-        commands.spawn((
-            Sprite {
-                image: bullet_texture.clone(),
-                ..Default::default()
-            },
-            Transform::from_xyz(transform.translation.x, y_pos, transform.translation.z)
-                .with_scale(Vec3::splat(resolution.pixel_ratio)),
-            projectile::Projectile {
-                speed: BULLET_SPEED,
-            },
-        ));
-
-        y_pos -= BULLET_HEIGHT;
+    for shot in shots {
+        pool.spawn(
+            commands,
+            (
+                Sprite {
+                    image: textures.chain.clone(),
+                    ..Default::default()
+                },
+                Transform::from_translation(transform.translation + shot.offset.extend(0.))
+                    .with_rotation(Quat::from_rotation_z(-shot.angle))
+                    .with_scale(Vec3::splat(resolution.pixel_ratio)),
+                projectile::Projectile {
+                    velocity: shot.velocity(speed),
+                    player,
+                    weapon: weapon.clone(),
+                    hits: 0,
+                },
+                projectile::COLLIDER,
+            ),
+        );
     }
 }
 
-const GUN_WIDTH: f32 = 20.0;
-
-fn spawn_two_missiles(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    resolution: &Res<resolution::Resolution>,
-    transform: &Mut<'_, Transform>,
-    num_missiles: i32,
+//every kill scores for the player whose shot started the chain
+fn award_points(
+    mut alien_killed_events: EventReader<AlienKilledEvent>,
+    mut ufo_killed_events: EventReader<UfoKilledEvent>,
+    mut player_query: Query<&mut Player>,
 ) {
-    let bullet_texture: Handle<Image> = asset_server.load("images/chain.png");
-    let mut y_pos = transform.translation.y + PRIMARY_GUN_HEIGHT;
-
-    for _ in 0..num_missiles {
-        commands.spawn((
-            Sprite {
-                image: bullet_texture.clone(),
-                ..Default::default()
-            },
-            Transform::from_xyz(
-                transform.translation.x - GUN_WIDTH,
-                y_pos,
-                transform.translation.z,
-            )
-            .with_scale(Vec3::splat(resolution.pixel_ratio)),
-            projectile::Projectile {
-                speed: BULLET_SPEED,
-            },
-        ));
-
-        commands.spawn((
-            Sprite {
-                image: bullet_texture.clone(),
-                ..Default::default()
-            },
-            Transform::from_xyz(
-                transform.translation.x + GUN_WIDTH,
-                y_pos,
-                transform.translation.z,
-            )
-            .with_scale(Vec3::splat(resolution.pixel_ratio)),
-            projectile::Projectile {
-                speed: BULLET_SPEED,
-            },
-        ));
+    for event in alien_killed_events.read() {
+        if let Ok(mut player) = player_query.get_mut(event.player) {
+            player.score += event.alien_type.points();
+        }
+    }
+    for event in ufo_killed_events.read() {
+        if let Ok(mut player) = player_query.get_mut(event.player) {
+            player.score += event.points;
+        }
+    }
+}
 
-        y_pos -= BULLET_HEIGHT;
+//a player who still has lives keeps playing, but loses their gun upgrades and goes back to the
+//chain gun
+fn player_hit(
+    mut player_hit_events: EventReader<PlayerHitEvent>,
+    mut player_query: Query<(&mut Player, &mut EquippedWeapon, &mut Visibility)>,
+) {
+    for event in player_hit_events.read() {
+        let Ok((mut player, mut weapon, mut visibility)) = player_query.get_mut(event.player)
+        else {
+            continue;
+        };
+        player.main_gun_projectiles = 1;
+        player.side_gun_projectiles = 0;
+        *weapon = EquippedWeapon::default();
+        if player.dead {
+            *visibility = Visibility::Hidden;
+        }
     }
 }
 
 fn reset_when_killed(
+    mut commands: Commands,
     mut player_killed_events: EventReader<PlayerKilledEvent>,
-    mut player_query: Query<&mut Player>,
+    mut player_query: Query<(Entity, &mut Player, &mut EquippedWeapon, &mut Visibility)>,
     mut events: EventWriter<LevelCompletedEvent>,
     mut score_manager: ResMut<ScoreManager>,
+    settings: Res<Settings>,
 ) {
-    let mut player = player_query.single_mut().unwrap();
     for _ in player_killed_events.read() {
         score_manager.curr_level = 0;
-        player.main_gun_projectiles = 1;
-        player.side_gun_projectiles = 0;
+        for (entity, mut player, mut weapon, mut visibility) in player_query.iter_mut() {
+            player.dead = false;
+            player.lives = settings.mode.lives();
+            player.score = 0;
+            player.main_gun_projectiles = 1;
+            player.side_gun_projectiles = 0;
+            *weapon = EquippedWeapon::default();
+            //when taking turns the first player goes first again
+            if settings.mode.takes_turns() && player.slot > 0 {
+                commands.entity(entity).insert(AwaitingTurn);
+                *visibility = Visibility::Hidden;
+            } else {
+                commands.entity(entity).remove::<AwaitingTurn>();
+                *visibility = Visibility::Inherited;
+            }
+        }
         events.write(LevelCompletedEvent {});
     }
 }
@@ -0,0 +1,163 @@
+//! Pools for the entities that come and go all the time: projectiles, fires and capsules.
+//!
+//! Instead of being despawned, a pooled entity is released back to its [`Pool`]. It gets
+//! [`Disabled`], which hides it from every query and from rendering, and from the next frame on
+//! waits there for a [`Pool::spawn`] to put a new bundle on it. Each pool starts with some
+//! entities spawned up front, so even a full volley at the top gun level reuses entities rather
+//! than making new ones. The images they are drawn with are loaded once into [`Textures`].
+
+use std::marker::PhantomData;
+
+use bevy::ecs::entity_disabling::Disabled;
+use bevy::ecs::error::ignore;
+use bevy::ecs::system::{SystemParam, entity_command};
+use bevy::prelude::*;
+
+use crate::alien_projectile::AlienProjectile;
+use crate::capsule::{Capsule, CapsuleCounter};
+use crate::fire::Fire;
+use crate::preload::Preload;
+use crate::projectile::Projectile;
+
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_textures);
+    }
+}
+
+//the images of the pooled entities
+#[derive(Resource)]
+pub struct Textures {
+    pub chain: Handle<Image>,
+    pub fire: Handle<Image>,
+    pub capsule: Handle<Image>,
+}
+
+//preloaded so capsules are fitted to their sprite as soon as they spawn, see `Collider::sprite`
+fn setup_textures(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preload: ResMut<Preload>,
+) {
+    commands.insert_resource(Textures {
+        chain: preload.load(&asset_server, "images/chain.png"),
+        fire: preload.load(&asset_server, "images/fire.png"),
+        capsule: preload.load(&asset_server, "images/orange_capsule.png"),
+    });
+}
+
+//the released entities that were last spawned with a `T`
+#[derive(Resource)]
+pub struct Pool<T: Component> {
+    free: Vec<Entity>,
+    //released this frame. Systems that ran before the release still see these entities as they
+    //were, and may release them again, so they are only reused once the frame is over
+    released: Vec<Entity>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> Default for Pool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            released: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> Pool<T> {
+    //puts `bundle` on a released entity, or on a new one if there are none left. Whatever the
+    //entity had the last time round is removed first, so it ends up just as if it was spawned
+    pub fn spawn(&mut self, commands: &mut Commands, bundle: impl Bundle) -> Entity {
+        //one that was despawned rather than released while it was waiting is dropped
+        while let Some(entity) = self.free.pop() {
+            if let Ok(mut pooled) = commands.get_entity(entity) {
+                pooled
+                    .queue_handled(entity_command::clear(), ignore)
+                    .try_insert(bundle);
+                return entity;
+            }
+        }
+        commands.spawn(bundle).id()
+    }
+
+    //takes the place of despawning. Releasing the same entity twice in a frame, e.g. when a
+    //projectile hits two things at once, only releases it once
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.released.contains(&entity) || self.free.contains(&entity) {
+            return;
+        }
+        if let Ok(mut pooled) = commands.get_entity(entity) {
+            pooled.try_insert(Disabled);
+            self.released.push(entity);
+        }
+    }
+
+    //how many entities are waiting to be reused
+    pub fn free(&self) -> usize {
+        self.free.len() + self.released.len()
+    }
+
+    fn recycle(&mut self) {
+        self.free.append(&mut self.released);
+    }
+}
+
+//the pooled entities that are in play, for clearing the field when a turn ends or a run starts
+//over
+#[derive(SystemParam)]
+pub struct InPlay<'w, 's> {
+    projectile_query: Query<'w, 's, Entity, With<Projectile>>,
+    alien_projectile_query: Query<'w, 's, Entity, With<AlienProjectile>>,
+    capsule_query: Query<'w, 's, Entity, With<Capsule>>,
+    capsule_counter_query: Query<'w, 's, &'static mut CapsuleCounter>,
+    fire_query: Query<'w, 's, &'static mut Fire>,
+    projectile_pool: ResMut<'w, Pool<Projectile>>,
+    alien_projectile_pool: ResMut<'w, Pool<AlienProjectile>>,
+    capsule_pool: ResMut<'w, Pool<Capsule>>,
+}
+
+impl InPlay<'_, '_> {
+    //releases every shot and capsule, and puts the fires out so they are counted as burnt out
+    pub fn clear(&mut self, commands: &mut Commands) {
+        for entity in self.projectile_query.iter() {
+            self.projectile_pool.release(commands, entity);
+        }
+        for entity in self.alien_projectile_query.iter() {
+            self.alien_projectile_pool.release(commands, entity);
+        }
+        for entity in self.capsule_query.iter() {
+            self.capsule_pool.release(commands, entity);
+        }
+        //otherwise the released capsules would still count towards the most that can fall at once
+        for mut counter in self.capsule_counter_query.iter_mut() {
+            counter.num_capsules = 0;
+        }
+        for mut fire in self.fire_query.iter_mut() {
+            fire.time_remaining = 0.;
+        }
+    }
+}
+
+pub trait PoolAppExt {
+    //keeps a pool of entities for `T`, starting with `size` of them
+    fn add_pool<T: Component>(&mut self, size: usize) -> &mut Self;
+}
+
+impl PoolAppExt for App {
+    fn add_pool<T: Component>(&mut self, size: usize) -> &mut Self {
+        self.init_resource::<Pool<T>>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, mut pool: ResMut<Pool<T>>| {
+                    for _ in 0..size {
+                        pool.free.push(commands.spawn(Disabled).id());
+                    }
+                },
+            )
+            .add_systems(Last, |mut pool: ResMut<Pool<T>>| pool.recycle())
+    }
+}
@@ -0,0 +1,34 @@
+//! The assets the rules of the game are read from, such as the difficulty curve and the bunker
+//! layouts.
+//!
+//! They are loaded through [`Preload`], which keeps track of them so [`env`](crate::env) can wait
+//! until they have all arrived before any game time passes, and every episode plays by them from
+//! the first step.
+
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+
+#[derive(Resource, Default)]
+pub struct Preload(Vec<UntypedHandle>);
+
+impl Preload {
+    pub fn load<'a, A: Asset>(
+        &mut self,
+        asset_server: &AssetServer,
+        path: impl Into<AssetPath<'a>>,
+    ) -> Handle<A> {
+        let handle = asset_server.load(path);
+        self.0.push(handle.clone().untyped());
+        handle
+    }
+}
+
+//whether any of the assets are still on their way
+pub fn loading(world: &World) -> bool {
+    let asset_server = world.resource::<AssetServer>();
+    world
+        .resource::<Preload>()
+        .0
+        .iter()
+        .any(|handle| asset_server.load_state(handle.id()).is_loading())
+}
@@ -1,62 +1,113 @@
+use std::sync::Arc;
+
 use bevy::prelude::*;
 
 use crate::alien;
+use crate::collider::{Collider, Layers};
+use crate::fly_in::FlyingIn;
+use crate::pool::{Pool, PoolAppExt};
 use crate::resolution;
+use crate::weapon::Weapon;
 pub struct ProjectilePlugin;
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (update_projectiles, update_alien_interactions));
         app.add_event::<AlienKilledEvent>();
+        app.add_pool::<Projectile>(POOL_SIZE);
     }
 }
 
+//a few volleys from two players at the top gun level
+const POOL_SIZE: usize = 64;
+
 #[derive(Event)]
 pub struct AlienKilledEvent {
     pub alien_type: alien::AlienType,
     pub location: Vec2,
+    //the player whose shot killed the alien or started the chain that did
+    pub player: Entity,
 }
 
 #[derive(Component)]
 pub struct Projectile {
-    pub speed: f32,
+    pub velocity: Vec2,
+    pub player: Entity,
+    //the weapon that fired it, which steers it and decides what happens when it hits
+    pub weapon: Arc<dyn Weapon>,
+    //the aliens it has killed
+    pub hits: u32,
 }
 //move the projectiles
 fn update_projectiles(
     mut commands: Commands,
-    mut projectile_query: Query<(Entity, &Projectile, &mut Transform)>,
+    mut projectile_query: Query<(Entity, &mut Projectile, &mut Transform)>,
+    alien_query: Query<
+        (&alien::Alien, &Transform),
+        (Without<alien::Dead>, Without<FlyingIn>, Without<Projectile>),
+    >,
+    mut pool: ResMut<Pool<Projectile>>,
     time: Res<Time>,
     resolution: Res<resolution::Resolution>,
 ) {
-    for (entity, projectile, mut transform) in projectile_query.iter_mut() {
-        transform.translation.y += projectile.speed * time.delta_secs();
-        if transform.translation.y > resolution.screen_dimensions.y * 0.5 {
-            commands.entity(entity).despawn();
+    let targets: Vec<Vec2> = alien_query
+        .iter()
+        .filter(|(alien, _)| !alien.dead)
+        .map(|(_, transform)| transform.translation.truncate())
+        .collect();
+    for (entity, mut projectile, mut transform) in projectile_query.iter_mut() {
+        let weapon = projectile.weapon.clone();
+        let position = transform.translation.truncate();
+        weapon.update(&mut projectile, position, &targets, time.delta_secs());
+        transform.translation += (projectile.velocity * time.delta_secs()).extend(0.);
+        //the chain points the way it is going
+        transform.rotation =
+            Quat::from_rotation_z(-projectile.velocity.x.atan2(projectile.velocity.y));
+        let half_screen = resolution.screen_dimensions * 0.5;
+        if transform.translation.y > half_screen.y || transform.translation.x.abs() > half_screen.x
+        {
+            pool.release(&mut commands, entity);
         }
     }
 }
-const BULLET_RADIUS: f32 = 16.;
+//the chain is 8 pixels across
+pub const COLLIDER: Collider = Collider::circle(4.)
+    .on(Layers::PLAYER_SHOT)
+    .hits(Layers::ALIEN.union(Layers::BUNKER).union(Layers::UFO));
+
 //activate death for aliens and such
 fn update_alien_interactions(
-    mut alien_query: Query<(&mut alien::Alien, &Transform), Without<alien::Dead>>,
-    mut projectile_query: Query<(Entity, &Transform), With<Projectile>>,
+    mut alien_query: Query<
+        (&mut alien::Alien, &Collider, &Transform),
+        (Without<alien::Dead>, Without<FlyingIn>),
+    >,
+    mut projectile_query: Query<(Entity, &mut Projectile, &Collider, &Transform)>,
     mut commands: Commands,
+    mut pool: ResMut<Pool<Projectile>>,
     mut events: EventWriter<AlienKilledEvent>,
 ) {
-    for (mut alien, alien_transform) in alien_query.iter_mut() {
-        for (projectile_entity, projectile_transform) in projectile_query.iter_mut() {
-            let projectile_pos = Vec2::new(
-                projectile_transform.translation.x,
-                projectile_transform.translation.y,
-            );
+    for (mut alien, alien_collider, alien_transform) in alien_query.iter_mut() {
+        //killed this frame, by a fire or a shot that carried on
+        if alien.dead {
+            continue;
+        }
+        for (projectile_entity, mut projectile, projectile_collider, projectile_transform) in
+            projectile_query.iter_mut()
+        {
             let alien_pos = Vec2::new(alien_transform.translation.x, alien_transform.translation.y);
-            if Vec2::distance(alien_pos, projectile_pos) < BULLET_RADIUS {
+            if projectile_collider.collides(projectile_transform, alien_collider, alien_transform) {
                 alien.dead = true;
-                commands.entity(projectile_entity).despawn();
+                projectile.hits += 1;
+                if !projectile.weapon.on_hit(&projectile) {
+                    pool.release(&mut commands, projectile_entity);
+                }
                 events.write(AlienKilledEvent {
                     alien_type: alien.alien_type,
                     location: alien_pos,
+                    player: projectile.player,
                 });
+                //one kill is enough, and any other shot here flies on
+                break;
             }
         }
     }
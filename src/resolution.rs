@@ -5,10 +5,17 @@ pub struct ResolutionPlugin;
 impl Plugin for ResolutionPlugin {
     fn build(&self, app: &mut App) {
         //prestartup runs before all of our in game startup functions
-        app.add_systems(PreStartup, setup_resolution);
+        //without a window, e.g. in `env`, the resolution is inserted up front instead
+        app.add_systems(
+            PreStartup,
+            setup_resolution.run_if(not(resource_exists::<Resolution>)),
+        );
     }
 }
 
+//the size of the window the game was designed for
+pub const WINDOW_SIZE: Vec2 = Vec2::new(612., 612.);
+
 #[derive(Resource)]
 pub struct Resolution {
     //pixel dimensions of our screen in the form of a 2d vector (width,height)
@@ -17,13 +24,22 @@ pub struct Resolution {
     pub pixel_ratio: f32,
 }
 
+impl Resolution {
+    pub fn new(screen_dimensions: Vec2) -> Self {
+        Self {
+            screen_dimensions,
+            pixel_ratio: 1.2,
+        }
+    }
+}
+
 fn setup_resolution(mut commands: Commands, window_query: Query<&Window>) {
     //query for window information
     let window = window_query.single();
     let win = window.unwrap();
 
-    commands.insert_resource(Resolution {
-        screen_dimensions: Vec2::new(win.resolution.width(), win.resolution.height()),
-        pixel_ratio: 1.2,
-    });
+    commands.insert_resource(Resolution::new(Vec2::new(
+        win.resolution.width(),
+        win.resolution.height(),
+    )));
 }
@@ -0,0 +1,25 @@
+//! The random numbers behind the gameplay, such as which alien shoots and which kill drops a
+//! capsule.
+//!
+//! The game seeds it from entropy; [`env`](crate::env) seeds it so an episode can be replayed.
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+//ChaCha8 rather than `StdRng`, which may change between versions of `rand` and with it every
+//episode recorded for a seed
+#[derive(Resource)]
+pub struct GameRng(pub ChaCha8Rng);
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self(ChaCha8Rng::from_entropy())
+    }
+}
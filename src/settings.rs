@@ -0,0 +1,66 @@
+//! Player settings, saved between sessions.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility::Accessibility;
+use crate::game_mode::GameMode;
+use crate::localization;
+use crate::storage;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        //`main` inserts the saved settings before the app is built; this covers everything else
+        if !app.world().contains_resource::<Settings>() {
+            app.insert_resource(Settings::load());
+        }
+        app.add_systems(Last, save_settings.run_if(resource_changed::<Settings>));
+    }
+}
+
+const SETTINGS_FILE: &str = "settings";
+
+#[derive(Resource, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    //the name of the soundtrack in `sounds/soundtracks.music.ron`
+    pub soundtrack: String,
+    //endless mode generates a new formation every level, from `endless_seed` or a random seed
+    pub endless: bool,
+    pub endless_seed: Option<u64>,
+    pub mode: GameMode,
+    //record gameplay events to a log file, see `event_log`
+    pub event_log: bool,
+    pub accessibility: Accessibility,
+    //one of `localization::LANGUAGES`
+    pub language: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            soundtrack: String::from("Classic"),
+            endless: false,
+            endless_seed: None,
+            mode: GameMode::Single,
+            event_log: false,
+            accessibility: Accessibility::default(),
+            language: String::from(localization::ENGLISH),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_FILE).unwrap_or_default()
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    //the settings we just loaded don't need writing back
+    if !settings.is_added() {
+        storage::save(SETTINGS_FILE, &*settings);
+    }
+}
@@ -0,0 +1,197 @@
+//! Data-driven sound effects.
+//!
+//! Sounds are described in `sounds/sound_effects.sfx.ron` and triggered by name. Gameplay events
+//! are mapped to a sound name with [`SoundEffectAppExt::add_sound_effect`], so tuning or swapping
+//! a sound only means editing the file.
+
+use std::collections::HashMap;
+
+use bevy::{audio::Volume, prelude::*};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::ron_asset::RonAssetPlugin;
+
+pub struct SoundEffectsPlugin;
+
+impl Plugin for SoundEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<SoundEffectRegistry>::new(&["sfx.ron"]))
+            .add_event::<PlaySoundEvent>()
+            .add_systems(Startup, setup_sound_effects)
+            .add_systems(Update, (update_cooldowns, play_sound_effects).chain());
+    }
+}
+
+const REGISTRY_PATH: &str = "sounds/sound_effects.sfx.ron";
+
+#[derive(Deserialize)]
+pub struct SoundEffect {
+    pub path: String,
+    //seconds before the same sound may play again
+    #[serde(default)]
+    pub cooldown: f32,
+    //the most copies of this sound that may be heard at once
+    #[serde(default = "default_max_voices")]
+    pub max_voices: usize,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+    //each play randomly offsets pitch and volume by up to these amounts
+    #[serde(default)]
+    pub pitch_variation: f32,
+    #[serde(default)]
+    pub volume_variation: f32,
+    //when every voice is busy, a sound may only replace one with a lower priority
+    #[serde(default)]
+    pub priority: i32,
+}
+
+fn default_max_voices() -> usize {
+    1
+}
+
+fn default_volume() -> f32 {
+    1.
+}
+
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SoundEffectRegistry {
+    //the most sound effects that may be heard at once across all sounds
+    pub max_voices: usize,
+    pub sounds: HashMap<String, SoundEffect>,
+}
+
+#[derive(Event, Debug)]
+pub struct PlaySoundEvent {
+    pub name: &'static str,
+}
+
+pub trait SoundEffectAppExt {
+    //play the named sound from the registry every time `E` is sent
+    fn add_sound_effect<E: Event>(&mut self, name: &'static str) -> &mut Self;
+}
+
+impl SoundEffectAppExt for App {
+    fn add_sound_effect<E: Event>(&mut self, name: &'static str) -> &mut Self {
+        self.add_systems(
+            Update,
+            (move |mut events: EventReader<E>, mut sounds: EventWriter<PlaySoundEvent>| {
+                for _ in events.read() {
+                    sounds.write(PlaySoundEvent { name });
+                }
+            })
+            .before(play_sound_effects),
+        )
+    }
+}
+
+#[derive(Resource)]
+struct SoundEffects {
+    registry: Handle<SoundEffectRegistry>,
+    cooldowns: HashMap<&'static str, f32>,
+}
+
+#[derive(Component)]
+struct SoundEffectVoice {
+    name: &'static str,
+    priority: i32,
+}
+
+fn setup_sound_effects(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundEffects {
+        registry: asset_server.load(REGISTRY_PATH),
+        cooldowns: HashMap::new(),
+    });
+}
+
+fn update_cooldowns(mut sound_effects: ResMut<SoundEffects>, time: Res<Time>) {
+    for timer in sound_effects.cooldowns.values_mut() {
+        *timer -= time.delta_secs();
+    }
+}
+
+fn play_sound_effects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<PlaySoundEvent>,
+    mut sound_effects: ResMut<SoundEffects>,
+    registries: Res<Assets<SoundEffectRegistry>>,
+    voice_query: Query<(Entity, &SoundEffectVoice)>,
+) {
+    let Some(registry) = registries.get(&sound_effects.registry) else {
+        events.clear();
+        return;
+    };
+
+    //higher priorities get the first pick of the free voices
+    let mut requests: Vec<(&'static str, &SoundEffect)> = Vec::new();
+    for event in events.read() {
+        match registry.sounds.get(event.name) {
+            Some(effect) => requests.push((event.name, effect)),
+            None => warn!("no sound effect named {} in {}", event.name, REGISTRY_PATH),
+        }
+    }
+    requests.sort_by_key(|(_, effect)| -effect.priority);
+
+    let mut voices: Vec<(Entity, &'static str, i32)> = voice_query
+        .iter()
+        .map(|(entity, voice)| (entity, voice.name, voice.priority))
+        .collect();
+    let mut rng = rand::thread_rng();
+
+    for (name, effect) in requests {
+        if sound_effects
+            .cooldowns
+            .get(name)
+            .is_some_and(|timer| *timer > 0.)
+        {
+            continue;
+        }
+        if voices.iter().filter(|voice| voice.1 == name).count() >= effect.max_voices {
+            continue;
+        }
+
+        if voices.len() >= registry.max_voices {
+            //steal the voice of the least important sound, if there is one less important than us
+            let Some(index) = voices
+                .iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.2 < effect.priority)
+                .min_by_key(|(_, voice)| voice.2)
+                .map(|(index, _)| index)
+            else {
+                continue;
+            };
+            commands.entity(voices[index].0).despawn();
+            voices.swap_remove(index);
+        }
+
+        let pitch = 1. + vary(&mut rng, effect.pitch_variation);
+        let volume = (effect.volume + vary(&mut rng, effect.volume_variation)).max(0.);
+        let entity = commands
+            .spawn((
+                AudioPlayer::new(asset_server.load(effect.path.clone())),
+                PlaybackSettings {
+                    volume: Volume::Linear(volume),
+                    speed: pitch,
+                    ..PlaybackSettings::DESPAWN
+                },
+                SoundEffectVoice {
+                    name,
+                    priority: effect.priority,
+                },
+            ))
+            .id();
+
+        voices.push((entity, name, effect.priority));
+        sound_effects.cooldowns.insert(name, effect.cooldown);
+    }
+}
+
+fn vary(rng: &mut impl Rng, amount: f32) -> f32 {
+    if amount > 0. {
+        rng.gen_range(-amount..=amount)
+    } else {
+        0.
+    }
+}
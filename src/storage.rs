@@ -0,0 +1,95 @@
+//! Saves small RON files such as settings, and session logs, to the player's data directory.
+//!
+//! Web builds have no file system, so nothing is persisted there.
+
+use serde::{Serialize, de::DeserializeOwned};
+
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir() -> std::path::PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| std::path::PathBuf::from(home).join(".local").join("share"))
+        })
+        .unwrap_or_default();
+
+    data_dir.join("chain_reaxian")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(name: &str) -> std::path::PathBuf {
+    data_dir().join(format!("{name}.ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = path(name);
+    let text = std::fs::read_to_string(&path).ok()?;
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            bevy::log::warn!("ignoring unreadable {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let path = path(name);
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|text| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+            }
+            std::fs::write(&path, text).map_err(|err| err.to_string())
+        });
+
+    if let Err(err) = result {
+        bevy::log::warn!("could not save {}: {}", path.display(), err);
+    }
+}
+
+//a new file under `dir` named after the time it was created, for logs that grow over a session.
+//returns the file with its path
+#[cfg(not(target_arch = "wasm32"))]
+pub fn create_session_file(
+    dir: &str,
+    extension: &str,
+) -> Option<(String, Box<dyn std::io::Write + Send + Sync>)> {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = data_dir()
+        .join(dir)
+        .join(format!("session-{seconds}.{extension}"));
+    let file = std::fs::create_dir_all(path.parent()?).and_then(|_| std::fs::File::create(&path));
+
+    match file {
+        Ok(file) => Some((path.display().to_string(), Box::new(file))),
+        Err(err) => {
+            bevy::log::warn!("could not create {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load<T: DeserializeOwned>(_name: &str) -> Option<T> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save<T: Serialize>(_name: &str, _value: &T) {}
+
+#[cfg(target_arch = "wasm32")]
+pub fn create_session_file(
+    _dir: &str,
+    _extension: &str,
+) -> Option<(String, Box<dyn std::io::Write + Send + Sync>)> {
+    None
+}
@@ -0,0 +1,138 @@
+//! The title screen and the attract mode demo.
+//!
+//! The game waits behind the title until a key is pressed. Left idle, the title gives way to a
+//! demo where the [`autopilot`](crate::autopilot) flies the ships, and any key brings the title
+//! back.
+
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::game_mode::NewRunEvent;
+use crate::localization::Localized;
+use crate::widget;
+
+pub struct TitleScreenPlugin;
+
+impl Plugin for TitleScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<Screen>()
+            .enable_state_scoped_entities::<Screen>()
+            .init_resource::<IdleTimer>()
+            .add_systems(OnEnter(Screen::Title), enter_title)
+            .add_systems(OnEnter(Screen::Demo), enter_demo)
+            .add_systems(OnEnter(Screen::Playing), enter_playing)
+            .add_systems(
+                Update,
+                (
+                    start_demo.run_if(in_state(Screen::Title)),
+                    leave_title.run_if(in_state(Screen::Title)),
+                    leave_demo.run_if(in_state(Screen::Demo)),
+                ),
+            );
+    }
+}
+
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Screen {
+    //the game is paused behind the title
+    #[default]
+    Title,
+    //the autopilot plays while nobody is
+    Demo,
+    Playing,
+    //the game is paused while a wave is edited, see `wave_editor`
+    Editor,
+}
+
+//seconds of no input on the title before the demo starts
+const IDLE_TIME: f32 = 10.;
+
+#[derive(Resource, Default)]
+struct IdleTimer(f32);
+
+fn enter_title(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut idle_timer: ResMut<IdleTimer>,
+    mut new_run_events: EventWriter<NewRunEvent>,
+) {
+    time.pause();
+    idle_timer.0 = 0.;
+    new_run_events.write(NewRunEvent {});
+
+    commands.spawn((
+        widget::ui_center_root("Title"),
+        GlobalZIndex(3),
+        BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+        StateScoped(Screen::Title),
+        children![
+            (widget::large_text(""), Localized::new("title")),
+            (widget::small_text(""), Localized::new("press_any_key")),
+        ],
+    ));
+}
+
+fn enter_demo(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut new_run_events: EventWriter<NewRunEvent>,
+) {
+    time.unpause();
+    new_run_events.write(NewRunEvent {});
+
+    commands.spawn((
+        Name::new("Demo"),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            bottom: Px(80.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        GlobalZIndex(3),
+        Pickable::IGNORE,
+        StateScoped(Screen::Demo),
+        children![(widget::large_text(""), Localized::new("demo"))],
+    ));
+}
+
+fn enter_playing(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn start_demo(
+    time: Res<Time<Real>>,
+    mut idle_timer: ResMut<IdleTimer>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    idle_timer.0 += time.delta_secs();
+    if idle_timer.0 > IDLE_TIME {
+        next_screen.set(Screen::Demo);
+    }
+}
+
+fn leave_title(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_query: Query<&Gamepad>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if any_input(&keys, &gamepad_query) {
+        next_screen.set(Screen::Playing);
+    }
+}
+
+fn leave_demo(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_query: Query<&Gamepad>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if any_input(&keys, &gamepad_query) {
+        next_screen.set(Screen::Title);
+    }
+}
+
+fn any_input(keys: &ButtonInput<KeyCode>, gamepad_query: &Query<&Gamepad>) -> bool {
+    keys.get_just_pressed().next().is_some()
+        || gamepad_query
+            .iter()
+            .any(|gamepad| gamepad.get_just_pressed().next().is_some())
+}
@@ -0,0 +1,241 @@
+//! The mystery UFO, a bonus saucer that now and then crosses the top of the screen.
+//!
+//! When the next one comes is rolled from [`GameRng`] around the level's `ufo_interval`, so it
+//! gets more frequent as the levels go up and a seeded run always sees the same saucers. Shooting
+//! it scores a random high score and always drops a capsule. Catching it in the fire of a chain
+//! reaction, only possible while the formation is still near the top, scores a bigger bonus and
+//! upgrades the guns of the player who started the chain.
+
+use bevy::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::alien::WaveStartedEvent;
+use crate::capsule::SpawnCapsuleEvent;
+use crate::collider::{Collider, Layers};
+use crate::difficulty::Difficulty;
+use crate::fire::Fire;
+use crate::localization::Localized;
+use crate::player::Player;
+use crate::pool::Pool;
+use crate::projectile::Projectile;
+use crate::resolution;
+use crate::rng::GameRng;
+use crate::widget;
+
+pub struct UfoPlugin;
+
+impl Plugin for UfoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UfoTimer>()
+            .add_event::<UfoAppearedEvent>()
+            .add_event::<UfoKilledEvent>()
+            .add_systems(
+                Update,
+                (
+                    (reset_ufo, spawn_ufo, update_ufo, update_ufo_interactions).chain(),
+                    destroy_ufo_text,
+                ),
+            );
+    }
+}
+
+//the mean number of seconds between saucers on the first level, see `Difficulty::ufo_interval`
+pub const INTERVAL: f32 = 25.;
+const SPEED: f32 = 110.;
+//from the top of the screen, just above the top row of a new wave
+const HEIGHT_FROM_TOP: f32 = 26.;
+//what shooting it may score
+const POINTS: [i32; 4] = [100, 150, 200, 300];
+const CHAIN_BONUS: i32 = 1000;
+const TEXT_TIME: f32 = 1.2;
+const ZINDEX: f32 = 15.;
+
+pub const COLLIDER: Collider = Collider::rect(28., 14.).on(Layers::UFO);
+
+#[derive(Component)]
+pub struct Ufo {
+    //1 heading right, -1 heading left
+    pub direction: f32,
+}
+
+#[derive(Event)]
+pub struct UfoAppearedEvent {}
+
+#[derive(Event)]
+pub struct UfoKilledEvent {
+    pub location: Vec2,
+    pub player: Entity,
+    pub points: i32,
+    //caught in a chain reaction rather than shot
+    pub by_fire: bool,
+}
+
+//seconds until the next saucer, rolled when the last one has gone
+#[derive(Resource, Default)]
+struct UfoTimer(Option<f32>);
+
+#[derive(Component)]
+struct UfoText {
+    time_remaining: f32,
+}
+
+//a new wave, or the same one started over, waits a whole interval for its saucer
+fn reset_ufo(
+    mut commands: Commands,
+    mut wave_started_events: EventReader<WaveStartedEvent>,
+    mut timer: ResMut<UfoTimer>,
+    ufo_query: Query<Entity, With<Ufo>>,
+) {
+    if wave_started_events.read().count() == 0 {
+        return;
+    }
+    timer.0 = None;
+    for entity in ufo_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn spawn_ufo(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    resolution: Res<resolution::Resolution>,
+    difficulty: Res<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<UfoTimer>,
+    mut events: EventWriter<UfoAppearedEvent>,
+    ufo_query: Query<(), With<Ufo>>,
+    time: Res<Time>,
+) {
+    if !ufo_query.is_empty() {
+        return;
+    }
+    let time_remaining = timer
+        .0
+        .get_or_insert_with(|| difficulty.ufo_interval * rng.0.gen_range(0.5..1.5));
+    *time_remaining -= time.delta_secs();
+    if *time_remaining > 0. {
+        return;
+    }
+    timer.0 = None;
+
+    let direction = if rng.0.gen_bool(0.5) { 1. } else { -1. };
+    let half_screen = resolution.screen_dimensions * 0.5;
+    events.write(UfoAppearedEvent {});
+    commands.spawn((
+        Name::new("UFO"),
+        Sprite {
+            image: asset_server.load("images/alien_queen.png"),
+            color: Color::srgb(1., 0.3, 0.5),
+            flip_y: true,
+            ..default()
+        },
+        Transform::from_xyz(
+            -direction * (half_screen.x + 40.),
+            half_screen.y - HEIGHT_FROM_TOP,
+            ZINDEX,
+        )
+        .with_scale(Vec3::new(
+            resolution.pixel_ratio * 1.4,
+            resolution.pixel_ratio,
+            1.,
+        )),
+        Ufo { direction },
+        COLLIDER,
+    ));
+}
+
+fn update_ufo(
+    mut commands: Commands,
+    mut ufo_query: Query<(Entity, &Ufo, &mut Transform)>,
+    resolution: Res<resolution::Resolution>,
+    time: Res<Time>,
+) {
+    for (entity, ufo, mut transform) in ufo_query.iter_mut() {
+        transform.translation.x += ufo.direction * SPEED * time.delta_secs();
+        //gone once it is off the other side
+        if transform.translation.x * ufo.direction > resolution.screen_dimensions.x * 0.5 + 40. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_ufo_interactions(
+    mut commands: Commands,
+    ufo_query: Query<(Entity, &Collider, &Transform), With<Ufo>>,
+    projectile_query: Query<(Entity, &Projectile, &Collider, &Transform)>,
+    fire_query: Query<(&Fire, &Collider, &Transform)>,
+    mut player_query: Query<&mut Player>,
+    mut projectile_pool: ResMut<Pool<Projectile>>,
+    mut rng: ResMut<GameRng>,
+    mut ufo_killed_events: EventWriter<UfoKilledEvent>,
+    mut spawn_capsule_events: EventWriter<SpawnCapsuleEvent>,
+) {
+    for (ufo_entity, ufo_collider, ufo_transform) in ufo_query.iter() {
+        let location = ufo_transform.translation.truncate();
+        let shot = projectile_query
+            .iter()
+            .find(|(_, _, collider, transform)| {
+                collider.collides(transform, ufo_collider, ufo_transform)
+            })
+            .map(|(entity, projectile, ..)| (entity, projectile.player));
+        let burnt = fire_query
+            .iter()
+            .find(|(_, collider, transform)| {
+                collider.collides(transform, ufo_collider, ufo_transform)
+            })
+            .map(|(fire, ..)| fire.player);
+
+        let event = if let Some(player) = burnt {
+            if let Ok(mut player) = player_query.get_mut(player) {
+                player.upgrade_guns();
+            }
+            UfoKilledEvent {
+                location,
+                player,
+                points: CHAIN_BONUS,
+                by_fire: true,
+            }
+        } else if let Some((projectile_entity, player)) = shot {
+            projectile_pool.release(&mut commands, projectile_entity);
+            spawn_capsule_events.write(SpawnCapsuleEvent { location });
+            UfoKilledEvent {
+                location,
+                player,
+                points: *POINTS.choose(&mut rng.0).unwrap(),
+                by_fire: false,
+            }
+        } else {
+            continue;
+        };
+
+        let mut text = commands.spawn((
+            Name::new("UFO Points"),
+            Text2d::new(event.points.to_string()),
+            TextFont::from_font_size(18.),
+            TextColor(widget::HEADER_TEXT),
+            Transform::from_translation(location.extend(ZINDEX)),
+            UfoText {
+                time_remaining: TEXT_TIME,
+            },
+        ));
+        if event.by_fire {
+            text.insert(Localized::new("ufo.chain_bonus").arg("points", event.points));
+        }
+        commands.entity(ufo_entity).despawn();
+        ufo_killed_events.write(event);
+    }
+}
+
+fn destroy_ufo_text(
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut UfoText)>,
+    time: Res<Time>,
+) {
+    for (entity, mut text) in text_query.iter_mut() {
+        text.time_remaining -= time.delta_secs();
+        if text.time_remaining < 0. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
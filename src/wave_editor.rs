@@ -0,0 +1,292 @@
+//! The wave editor, only built with the `dev` feature.
+//!
+//! Press F2 while playing to pause and lay out the wave over the play area: click the grid to
+//! place the chosen alien, right click to clear a cell. The panel sizes the grid, spaces it out
+//! and saves or loads it as a [`wave_file`] under `assets`. F2 again test plays the wave from the
+//! top.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::bevy_egui::{EguiContextPass, EguiContexts};
+use bevy_inspector_egui::egui;
+
+use crate::alien::{AlienManager, Formation};
+use crate::alien_layouts::{ALIEN_COLS, ALIEN_ROWS};
+use crate::alien_projectile::AlienProjectile;
+use crate::fire::Fire;
+use crate::pool::Pool;
+use crate::projectile::Projectile;
+use crate::title_screen::Screen;
+use crate::wave_file::{self, WaveFile};
+
+pub struct WaveEditorPlugin;
+
+impl Plugin for WaveEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveEditor>()
+            .add_systems(OnEnter(Screen::Editor), enter_editor)
+            .add_systems(OnExit(Screen::Editor), leave_editor)
+            .add_systems(
+                Update,
+                (
+                    toggle_editor.run_if(in_state(Screen::Playing).or(in_state(Screen::Editor))),
+                    (paint_cells, draw_grid)
+                        .chain()
+                        .run_if(in_state(Screen::Editor)),
+                ),
+            )
+            .add_systems(
+                EguiContextPass,
+                editor_panel.run_if(in_state(Screen::Editor)),
+            );
+    }
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+const DEFAULT_PATH: &str = "waves/custom.ron";
+
+const BRUSHES: [(i32, &str); 4] = [(1, "Worker"), (2, "Soldier"), (3, "Queen"), (0, "Empty")];
+
+//the wave being edited, kept between visits to the editor
+#[derive(Resource)]
+struct WaveEditor {
+    wave: WaveFile,
+    //what a left click puts in a cell
+    brush: i32,
+    path: String,
+    //the result of the last save or load
+    status: String,
+}
+
+impl Default for WaveEditor {
+    fn default() -> Self {
+        Self {
+            wave: WaveFile::new(&Formation::default()),
+            brush: 1,
+            path: String::from(DEFAULT_PATH),
+            status: String::new(),
+        }
+    }
+}
+
+impl WaveEditor {
+    //the cell of the grid at a point in the world, if any
+    fn cell_at(&self, formation: &Formation, point: Vec2) -> Option<(usize, usize)> {
+        let half_cell = formation.spacing * 0.5;
+        (0..self.wave.rows())
+            .flat_map(|row| (0..self.wave.cols()).map(move |col| (row, col)))
+            .find(|(row, col)| {
+                let center = formation.position(*row, *col);
+                let offset = (point - center).abs();
+                offset.x <= half_cell.x && offset.y <= half_cell.y
+            })
+    }
+}
+
+fn toggle_editor(
+    keys: Res<ButtonInput<KeyCode>>,
+    screen: Res<State<Screen>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+    next_screen.set(match screen.get() {
+        Screen::Editor => Screen::Playing,
+        _ => Screen::Editor,
+    });
+}
+
+//pauses the game with the wave back where it started and nothing else flying about
+fn enter_editor(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    mut editor: ResMut<WaveEditor>,
+    formation: Res<Formation>,
+    mut alien_manager: ResMut<AlienManager>,
+    projectile_query: Query<Entity, With<Projectile>>,
+    alien_projectile_query: Query<Entity, With<AlienProjectile>>,
+    mut projectile_pool: ResMut<Pool<Projectile>>,
+    mut alien_projectile_pool: ResMut<Pool<AlienProjectile>>,
+    mut fire_query: Query<&mut Fire>,
+) {
+    time.pause();
+    alien_manager.reset = true;
+    for entity in projectile_query.iter() {
+        projectile_pool.release(&mut commands, entity);
+    }
+    for entity in alien_projectile_query.iter() {
+        alien_projectile_pool.release(&mut commands, entity);
+    }
+    //burnt out, so they go on the next update even with the game paused
+    for mut fire in fire_query.iter_mut() {
+        fire.time_remaining = -1.;
+    }
+
+    //the formation may have moved on since the editor was last open, e.g. in endless mode
+    if editor.wave.formation() != *formation {
+        editor.wave = WaveFile::new(&formation);
+    }
+}
+
+fn leave_editor(
+    mut formation: ResMut<Formation>,
+    editor: Res<WaveEditor>,
+    mut alien_manager: ResMut<AlienManager>,
+) {
+    *formation = editor.wave.formation();
+    alien_manager.reset = true;
+}
+
+fn paint_cells(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut contexts: EguiContexts,
+    window_query: Query<&Window>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut editor: ResMut<WaveEditor>,
+    mut formation: ResMut<Formation>,
+) {
+    let value = if mouse_buttons.pressed(MouseButton::Left) {
+        editor.brush
+    } else if mouse_buttons.pressed(MouseButton::Right) {
+        0
+    } else {
+        return;
+    };
+    //clicks on the panel are for the panel
+    if contexts
+        .try_ctx_mut()
+        .is_some_and(|ctx| ctx.is_pointer_over_area() || ctx.is_using_pointer())
+    {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.single(), camera_query.single())
+    else {
+        return;
+    };
+    let Some(point) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    //dragging paints every cell it passes over, but the wave is only replaced when one changes
+    if let Some((row, col)) = editor.cell_at(&formation, point)
+        && editor.wave.mask[row][col] != value
+    {
+        editor.wave.mask[row][col] = value;
+        *formation = editor.wave.formation();
+    }
+}
+
+fn draw_grid(mut gizmos: Gizmos, editor: Res<WaveEditor>, formation: Res<Formation>) {
+    for row in 0..editor.wave.rows() {
+        for col in 0..editor.wave.cols() {
+            let center = formation.position(row, col);
+            gizmos.rect_2d(
+                Isometry2d::from_translation(center),
+                formation.spacing,
+                Color::srgba(1., 1., 1., 0.25),
+            );
+        }
+    }
+}
+
+fn editor_panel(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<WaveEditor>,
+    mut formation: ResMut<Formation>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+    let editor = &mut *editor;
+    let mut changed = false;
+
+    egui::Window::new("Wave Editor").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            for (value, name) in BRUSHES {
+                ui.selectable_value(&mut editor.brush, value, name);
+            }
+        });
+        ui.label("Left click places, right click clears");
+        ui.separator();
+
+        let (mut rows, mut cols) = (editor.wave.rows(), editor.wave.cols());
+        let resized = ui
+            .add(egui::Slider::new(&mut rows, 1..=ALIEN_ROWS).text("Rows"))
+            .changed()
+            | ui.add(egui::Slider::new(&mut cols, 1..=ALIEN_COLS).text("Columns"))
+                .changed();
+        if resized {
+            editor.wave.resize(rows, cols);
+            changed = true;
+        }
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut editor.wave.horizontal_spacing, 10.0..=30.0)
+                    .text("Column spacing"),
+            )
+            .changed();
+        changed |= ui
+            .add(
+                egui::Slider::new(&mut editor.wave.vertical_spacing, 20.0..=60.0)
+                    .text("Row spacing"),
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            if ui.button("Clear").clicked() {
+                editor
+                    .wave
+                    .mask
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|cell| *cell = 0);
+                changed = true;
+            }
+            if ui.button("Default wave").clicked() {
+                editor.wave = WaveFile::new(&Formation::default());
+                changed = true;
+            }
+        });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("assets/");
+            ui.text_edit_singleline(&mut editor.path);
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                editor.status = match wave_file::write(&editor.path, &editor.wave) {
+                    Ok(()) => format!("Saved {}", editor.path),
+                    Err(err) => err,
+                };
+            }
+            if ui.button("Load").clicked() {
+                editor.status = match wave_file::read(&editor.path) {
+                    Ok(wave) => {
+                        editor.wave = wave;
+                        changed = true;
+                        format!("Loaded {}", editor.path)
+                    }
+                    Err(err) => err,
+                };
+            }
+        });
+        if !editor.status.is_empty() {
+            ui.label(&editor.status);
+        }
+        ui.separator();
+
+        if ui.button("Test play (F2)").clicked() {
+            next_screen.set(Screen::Playing);
+        }
+    });
+
+    if changed {
+        *formation = editor.wave.formation();
+    }
+}
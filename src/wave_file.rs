@@ -0,0 +1,208 @@
+//! Formations saved as RON files under `assets`, e.g. `waves/boss.ron`, so a wave can be tried
+//! out without editing `DEFAULT_MASK` and recompiling.
+//!
+//! A file holds a grid of up to `ALIEN_ROWS` by `ALIEN_COLS` cells, from the bottom left corner of
+//! the formation, and the spacing between them. Files are read and written straight from disk rather than through
+//! the asset server so they can be used the moment they are asked for. Web builds have no file
+//! system to use.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::alien::{DEFAULT_SPACING, Formation};
+use crate::alien_layouts::{ALIEN_COLS, ALIEN_ROWS};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WaveFile {
+    //laid out like `DEFAULT_MASK`, bottom row first. missing rows and columns are left empty
+    pub mask: Vec<Vec<i32>>,
+    #[serde(default = "default_horizontal_spacing")]
+    pub horizontal_spacing: f32,
+    #[serde(default = "default_vertical_spacing")]
+    pub vertical_spacing: f32,
+}
+
+fn default_horizontal_spacing() -> f32 {
+    DEFAULT_SPACING.x
+}
+
+fn default_vertical_spacing() -> f32 {
+    DEFAULT_SPACING.y
+}
+
+impl WaveFile {
+    //the whole of a formation
+    pub fn new(formation: &Formation) -> Self {
+        Self {
+            mask: formation.mask.iter().map(|row| row.to_vec()).collect(),
+            horizontal_spacing: formation.spacing.x,
+            vertical_spacing: formation.spacing.y,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.mask.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.mask.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    //changes the size of the grid, keeping what is left in it. Every row ends up `cols` long
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.mask.resize(rows, Vec::new());
+        for row in self.mask.iter_mut() {
+            row.resize(cols, 0);
+        }
+    }
+
+    pub fn formation(&self) -> Formation {
+        let mut mask = [[0; ALIEN_COLS]; ALIEN_ROWS];
+        for (row, cells) in self.mask.iter().enumerate() {
+            mask[row][..cells.len()].copy_from_slice(cells);
+        }
+        Formation {
+            mask,
+            spacing: Vec2::new(self.horizontal_spacing, self.vertical_spacing),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.rows() > ALIEN_ROWS {
+            return Err(format!("a wave has at most {ALIEN_ROWS} rows"));
+        }
+        if self.cols() > ALIEN_COLS {
+            return Err(format!("a wave has at most {ALIEN_COLS} columns"));
+        }
+        if let Some(cell) = self
+            .mask
+            .iter()
+            .flatten()
+            .find(|cell| !(0..=3).contains(*cell))
+        {
+            return Err(format!("{cell} is not an alien type"));
+        }
+        if self.horizontal_spacing <= 0. || self.vertical_spacing <= 0. {
+            return Err(String::from("the spacing must be more than 0"));
+        }
+        Ok(())
+    }
+}
+
+pub fn load(path: &str) -> Result<Formation, String> {
+    read(path).map(|wave| wave.formation())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn full_path(path: &str) -> std::path::PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(path)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &str) -> Result<WaveFile, String> {
+    let full_path = full_path(path);
+    let text = std::fs::read_to_string(&full_path)
+        .map_err(|err| format!("could not read {}: {err}", full_path.display()))?;
+    let mut wave: WaveFile =
+        ron::from_str(&text).map_err(|err| format!("could not parse {path}: {err}"))?;
+    wave.validate()?;
+    //the editor paints on a whole grid, so short rows are filled out with empty cells
+    let (rows, cols) = (wave.rows(), wave.cols());
+    wave.resize(rows, cols);
+    Ok(wave)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(path: &str, wave: &WaveFile) -> Result<(), String> {
+    wave.validate()?;
+    let full_path = full_path(path);
+    //each row of the mask on a line of its own
+    let config = ron::ser::PrettyConfig::default().depth_limit(2);
+    let text = ron::ser::to_string_pretty(wave, config)
+        .map_err(|err| format!("could not write {path}: {err}"))?;
+    if let Some(dir) = full_path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| format!("could not create {}: {err}", dir.display()))?;
+    }
+    std::fs::write(&full_path, text)
+        .map_err(|err| format!("could not write {}: {err}", full_path.display()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(_path: &str) -> Result<WaveFile, String> {
+    Err(String::from("wave files can't be read on the web"))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(_path: &str, _wave: &WaveFile) -> Result<(), String> {
+    Err(String::from("wave files can't be written on the web"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alien_layouts::DEFAULT_MASK;
+
+    fn wave(mask: Vec<Vec<i32>>) -> WaveFile {
+        WaveFile {
+            mask,
+            horizontal_spacing: DEFAULT_SPACING.x,
+            vertical_spacing: DEFAULT_SPACING.y,
+        }
+    }
+
+    #[test]
+    fn validate_checks_size_cells_and_spacing() {
+        assert!(wave(vec![vec![1, 2, 3], vec![1]]).validate().is_ok());
+        assert!(wave(vec![vec![1]; ALIEN_ROWS + 1]).validate().is_err());
+        assert!(wave(vec![vec![1; ALIEN_COLS + 1]]).validate().is_err());
+        assert!(wave(vec![vec![1, 4]]).validate().is_err());
+        assert!(wave(vec![vec![-1]]).validate().is_err());
+
+        let mut flat = wave(vec![vec![1]]);
+        flat.vertical_spacing = 0.;
+        assert!(flat.validate().is_err());
+    }
+
+    #[test]
+    fn resize_keeps_what_fits() {
+        let mut grid = wave(vec![vec![1, 2, 3], vec![3]]);
+        grid.resize(3, 2);
+        assert_eq!(grid.mask, vec![vec![1, 2], vec![3, 0], vec![0, 0]]);
+        grid.resize(1, 4);
+        assert_eq!(grid.mask, vec![vec![1, 2, 0, 0]]);
+    }
+
+    #[test]
+    fn cells_start_from_the_bottom_left() {
+        let formation = wave(vec![vec![1, 0, 2], vec![3]]).formation();
+        assert_eq!(formation.mask[0][..4], [1, 0, 2, 0]);
+        assert_eq!(formation.mask[1][..2], [3, 0]);
+        assert!(formation.mask[2..].iter().flatten().all(|cell| *cell == 0));
+        assert!(formation.mask[0][3..].iter().all(|cell| *cell == 0));
+    }
+
+    #[test]
+    fn formation_round_trips() {
+        let formation = Formation {
+            mask: DEFAULT_MASK,
+            spacing: Vec2::new(18., 40.),
+        };
+        let wave = WaveFile::new(&formation);
+        assert!(wave.validate().is_ok());
+        assert!(wave.formation() == formation);
+
+        //through the text of a file too
+        let text = ron::ser::to_string(&wave).unwrap();
+        let read: WaveFile = ron::from_str(&text).unwrap();
+        assert!(read.formation() == formation);
+    }
+
+    #[test]
+    fn shipped_waves_are_valid() {
+        let boss: WaveFile = ron::from_str(include_str!("../assets/waves/boss.ron")).unwrap();
+        assert!(boss.validate().is_ok());
+    }
+}
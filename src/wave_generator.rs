@@ -0,0 +1,111 @@
+//! Procedurally generated formations for endless mode.
+//!
+//! A formation only depends on the seed and the level, so a seed always plays out the same
+//! sequence of waves. Formations are mirrored around the middle of the grid, keep the alien types
+//! in bands with queens at the top, and grow denser and gain queens as the level rises.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::alien_layouts::*;
+
+const EMPTY: i32 = 0;
+const WORKER: i32 = 1;
+const SOLDIER: i32 = 2;
+const QUEEN: i32 = 3;
+
+//aliens sit on every other column like in `DEFAULT_MASK`, so each row has this many slots
+const SLOTS: usize = ALIEN_COLS / 2;
+const PAIRS: usize = SLOTS / 2;
+
+//the share of slots that are filled on the first level, and how much that grows every level
+const INITIAL_DENSITY: f32 = 0.4;
+const DENSITY_INCREMENT: f32 = 0.05;
+const MAX_DENSITY: f32 = 0.95;
+
+//queens come in mirrored pairs; another pair joins every few levels
+const LEVELS_PER_QUEEN_PAIR: i32 = 4;
+
+//a wave always has at least this many aliens and a full pair in its bottom row
+const MIN_ALIENS: usize = 8;
+
+pub fn generate(seed: u64, level: i32) -> AlienMask {
+    let level = level.max(1);
+    //`StdRng` may change between versions of `rand`, but ChaCha8 gives the same waves for a seed
+    let mut rng =
+        ChaCha8Rng::seed_from_u64(seed ^ (level as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+    let density = (INITIAL_DENSITY + DENSITY_INCREMENT * (level - 1) as f32).min(MAX_DENSITY);
+    //soldiers start to appear among the workers in later levels
+    let soldier_chance = (0.05 * (level - 1) as f32).min(0.5);
+    let queen_pairs = (1 + (level - 1) / LEVELS_PER_QUEEN_PAIR).min(PAIRS as i32) as usize;
+
+    loop {
+        let mut mask = [[EMPTY; ALIEN_COLS]; ALIEN_ROWS];
+
+        for row in 0..ALIEN_ROWS - 1 {
+            let soldier_row = row == ALIEN_ROWS - 2;
+            for pair in 0..PAIRS {
+                if !rng.gen_bool(density as f64) {
+                    continue;
+                }
+                let alien = if soldier_row || rng.gen_bool(soldier_chance as f64) {
+                    SOLDIER
+                } else {
+                    WORKER
+                };
+                place_pair(&mut mask, row, pair, alien);
+            }
+        }
+
+        //queens fill the top row from the middle outwards
+        for pair in PAIRS - queen_pairs..PAIRS {
+            place_pair(&mut mask, ALIEN_ROWS - 1, pair, QUEEN);
+        }
+
+        if is_playable(&mask) {
+            return mask;
+        }
+    }
+}
+
+//pair 0 is the outermost pair of slots and `PAIRS - 1` the innermost
+fn place_pair(mask: &mut AlienMask, row: usize, pair: usize, alien: i32) {
+    let left = pair * 2;
+    let right = (SLOTS - 1 - pair) * 2;
+    mask[row][left] = alien;
+    mask[row][right] = alien;
+}
+
+//rejects sparse waves and ones floating above an empty bottom row, which look broken
+fn is_playable(mask: &AlienMask) -> bool {
+    let count = mask
+        .iter()
+        .flatten()
+        .filter(|alien| **alien != EMPTY)
+        .count();
+    let bottom_row = mask[0].iter().filter(|alien| **alien != EMPTY).count();
+    count >= MIN_ALIENS && bottom_row >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_waves() {
+        for level in 1..=20 {
+            assert_eq!(generate(42, level), generate(42, level));
+        }
+    }
+
+    #[test]
+    fn different_seed_different_waves() {
+        let waves = |seed| {
+            (1..=20)
+                .map(|level| generate(seed, level))
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(waves(42), waves(43));
+    }
+}
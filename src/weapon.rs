@@ -0,0 +1,309 @@
+//! The player's guns.
+//!
+//! A [`Weapon`] says where a volley's projectiles start, which way they go, how fast and how long
+//! the ship waits between volleys. Every projectile keeps the weapon that fired it, which can also
+//! steer it in flight and decide what happens when it hits. The ship fires whatever is in its
+//! [`EquippedWeapon`], so a new weapon only needs to implement the trait and be put there, by a
+//! capsule that carries it or with the `weapon` console command. Weapons grow with the ship's gun
+//! upgrades, see `Player::upgrade_guns`.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::difficulty::Difficulty;
+use crate::player::Player;
+use crate::projectile::Projectile;
+
+//from the ship's centre to the front of its main gun
+pub const PRIMARY_GUN_HEIGHT: f32 = 25.0;
+//between shots stacked in the same gun
+const BULLET_HEIGHT: f32 = 12.0;
+//from the ship's centre to each side gun
+const GUN_WIDTH: f32 = 20.0;
+
+//one projectile of a volley
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shot {
+    //from the ship's centre, in world units
+    pub offset: Vec2,
+    //radians from straight up, positive to the right
+    pub angle: f32,
+}
+
+impl Shot {
+    pub fn straight(offset: Vec2) -> Self {
+        Self { offset, angle: 0. }
+    }
+
+    //which way the shot travels at `speed`
+    pub fn velocity(&self, speed: f32) -> Vec2 {
+        Vec2::new(self.angle.sin(), self.angle.cos()) * speed
+    }
+}
+
+pub trait Weapon: Send + Sync + 'static {
+    //what it is called in the console
+    fn name(&self) -> &'static str;
+
+    //the fire pattern, for a ship with `player`'s gun upgrades
+    fn shots(&self, player: &Player) -> Vec<Shot>;
+
+    fn speed(&self, difficulty: &Difficulty) -> f32 {
+        difficulty.player_bullet_speed
+    }
+
+    fn cooldown(&self, difficulty: &Difficulty) -> f32 {
+        difficulty.player_shoot_cooldown
+    }
+
+    //called every frame for each of its projectiles in flight, at `position`, with `targets` the
+    //aliens that can be hit. Shots fly straight on unless the weapon turns their velocity
+    fn update(
+        &self,
+        _projectile: &mut Projectile,
+        _position: Vec2,
+        _targets: &[Vec2],
+        _delta: f32,
+    ) {
+    }
+
+    //called when one of its projectiles kills an alien, after `Projectile::hits` has been counted.
+    //Returns whether the projectile carries on rather than being used up
+    fn on_hit(&self, _projectile: &Projectile) -> bool {
+        false
+    }
+
+    //the tint of a capsule that carries the weapon
+    fn color(&self) -> Color {
+        Color::WHITE
+    }
+}
+
+//the weapon a ship fires, the chain gun until something swaps it
+#[derive(Component, Clone)]
+pub struct EquippedWeapon(pub Arc<dyn Weapon>);
+
+impl Default for EquippedWeapon {
+    fn default() -> Self {
+        Self(Arc::new(ChainGun))
+    }
+}
+
+//the names the console knows, see `by_name`. Capsules can carry all but the chain gun
+pub const NAMES: [&str; 4] = ["chain", "spread", "pierce", "homing"];
+
+pub fn by_name(name: &str) -> Option<Arc<dyn Weapon>> {
+    match name {
+        "chain" => Some(Arc::new(ChainGun)),
+        "spread" => Some(Arc::new(SpreadShot::default())),
+        "pierce" => Some(Arc::new(PiercingShot::default())),
+        "homing" => Some(Arc::new(HomingShot::default())),
+        _ => None,
+    }
+}
+
+//a column of shots stacked behind each other, the first at the front of the gun
+fn column(x: f32, count: i32) -> impl Iterator<Item = Shot> {
+    (0..count).map(move |index| {
+        Shot::straight(Vec2::new(
+            x,
+            PRIMARY_GUN_HEIGHT - index as f32 * BULLET_HEIGHT,
+        ))
+    })
+}
+
+//the original gun: chains straight up from the main gun, with a pair of side guns once upgraded
+pub struct ChainGun;
+
+impl Weapon for ChainGun {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    fn shots(&self, player: &Player) -> Vec<Shot> {
+        column(0., player.main_gun_projectiles)
+            .chain(column(-GUN_WIDTH, player.side_gun_projectiles))
+            .chain(column(GUN_WIDTH, player.side_gun_projectiles))
+            .collect()
+    }
+}
+
+//every chain leaves the main gun at once in a fan, trading reach for a slower reload
+pub struct SpreadShot {
+    //radians between neighbouring chains
+    pub spread: f32,
+    //how much longer it takes to reload than the chain gun
+    pub cooldown_scale: f32,
+}
+
+impl Default for SpreadShot {
+    fn default() -> Self {
+        Self {
+            spread: 0.15,
+            cooldown_scale: 1.3,
+        }
+    }
+}
+
+impl Weapon for SpreadShot {
+    fn name(&self) -> &'static str {
+        "spread"
+    }
+
+    //as many chains as the chain gun fires, plus one on each side to start with
+    fn shots(&self, player: &Player) -> Vec<Shot> {
+        let count = player.main_gun_projectiles + player.side_gun_projectiles * 2 + 2;
+        let middle = (count - 1) as f32 * 0.5;
+        (0..count)
+            .map(|index| Shot {
+                offset: Vec2::new(0., PRIMARY_GUN_HEIGHT),
+                angle: (index as f32 - middle) * self.spread,
+            })
+            .collect()
+    }
+
+    fn cooldown(&self, difficulty: &Difficulty) -> f32 {
+        difficulty.player_shoot_cooldown * self.cooldown_scale
+    }
+
+    fn color(&self) -> Color {
+        Color::srgb(0.5, 0.8, 1.)
+    }
+}
+
+//chains that tear through several aliens before they are used up, but fly slower
+pub struct PiercingShot {
+    //how many aliens a chain kills before it is used up
+    pub pierce: u32,
+    pub speed_scale: f32,
+}
+
+impl Default for PiercingShot {
+    fn default() -> Self {
+        Self {
+            pierce: 3,
+            speed_scale: 0.8,
+        }
+    }
+}
+
+impl Weapon for PiercingShot {
+    fn name(&self) -> &'static str {
+        "pierce"
+    }
+
+    fn shots(&self, player: &Player) -> Vec<Shot> {
+        ChainGun.shots(player)
+    }
+
+    fn speed(&self, difficulty: &Difficulty) -> f32 {
+        difficulty.player_bullet_speed * self.speed_scale
+    }
+
+    fn on_hit(&self, projectile: &Projectile) -> bool {
+        projectile.hits < self.pierce
+    }
+
+    fn color(&self) -> Color {
+        Color::srgb(1., 0.4, 0.9)
+    }
+}
+
+//chains that turn towards the nearest alien ahead of them
+pub struct HomingShot {
+    //radians a second a chain can turn
+    pub turn_rate: f32,
+}
+
+impl Default for HomingShot {
+    fn default() -> Self {
+        Self { turn_rate: 2.5 }
+    }
+}
+
+impl Weapon for HomingShot {
+    fn name(&self) -> &'static str {
+        "homing"
+    }
+
+    fn shots(&self, player: &Player) -> Vec<Shot> {
+        ChainGun.shots(player)
+    }
+
+    fn update(&self, projectile: &mut Projectile, position: Vec2, targets: &[Vec2], delta: f32) {
+        let heading = projectile.velocity.normalize_or_zero();
+        let Some(target) = targets
+            .iter()
+            .map(|target| *target - position)
+            .filter(|offset| offset.dot(heading) > 0.)
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        else {
+            return;
+        };
+        let turn = heading
+            .angle_to(target)
+            .clamp(-self.turn_rate * delta, self.turn_rate * delta);
+        projectile.velocity = Vec2::from_angle(turn).rotate(projectile.velocity);
+    }
+
+    fn color(&self) -> Color {
+        Color::srgb(0.5, 1., 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projectile(weapon: Arc<dyn Weapon>, velocity: Vec2) -> Projectile {
+        Projectile {
+            velocity,
+            player: Entity::PLACEHOLDER,
+            weapon,
+            hits: 0,
+        }
+    }
+
+    #[test]
+    fn every_name_is_a_weapon() {
+        for name in NAMES {
+            assert_eq!(by_name(name).map(|weapon| weapon.name()), Some(name));
+        }
+        assert!(by_name("laser").is_none());
+    }
+
+    #[test]
+    fn piercing_shots_last_for_their_kills() {
+        let weapon = by_name("pierce").unwrap();
+        let mut shot = projectile(weapon.clone(), Vec2::Y);
+        let carried_on: Vec<bool> = (0..4)
+            .map(|_| {
+                shot.hits += 1;
+                weapon.on_hit(&shot)
+            })
+            .collect();
+        assert_eq!(carried_on, [true, true, false, false]);
+        assert!(!ChainGun.on_hit(&projectile(Arc::new(ChainGun), Vec2::Y)));
+    }
+
+    #[test]
+    fn homing_shots_turn_towards_the_nearest_alien_ahead() {
+        let weapon = HomingShot { turn_rate: 1. };
+        let turned = |targets: &[Vec2], delta| {
+            let mut shot = projectile(Arc::new(ChainGun), Vec2::new(0., 100.));
+            weapon.update(&mut shot, Vec2::ZERO, targets, delta);
+            shot.velocity
+        };
+        //no further than the turn rate allows, and at the same speed
+        let velocity = turned(&[Vec2::new(100., 0.1), Vec2::new(-50., 200.)], 0.1);
+        assert!((velocity.to_angle() - (std::f32::consts::FRAC_PI_2 - 0.1)).abs() < 1e-4);
+        assert!((velocity.length() - 100.).abs() < 1e-3);
+        //straight at a target that is nearly dead ahead
+        let velocity = turned(&[Vec2::new(1., 100.)], 1.);
+        assert!(velocity.angle_to(Vec2::new(1., 100.)).abs() < 1e-3);
+        //aliens behind it are left alone
+        assert_eq!(turned(&[Vec2::new(10., -5.)], 1.), Vec2::new(0., 100.));
+        assert_eq!(turned(&[], 1.), Vec2::new(0., 100.));
+    }
+}
@@ -23,13 +23,46 @@ pub fn ui_center_root(name: impl Into<Cow<'static, str>>) -> impl Bundle {
     )
 }
 
+/// A root UI node across the top of the window for short notices such as achievements.
+pub fn ui_toast_root(name: impl Into<Cow<'static, str>>) -> impl Bundle {
+    (
+        Name::new(name),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Percent(100.0),
+            top: Px(60.0),
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(6.0),
+            ..default()
+        },
+        Pickable::IGNORE,
+    )
+}
+
 pub const HEADER_TEXT: Color = Color::srgb(0.988, 0.984, 0.800);
 
+/// The font size text was made with, before the large text option scales it. See
+/// [`accessibility`](crate::accessibility).
+#[derive(Component)]
+pub struct BaseFontSize(pub f32);
+
 pub fn large_text(text: impl Into<String>) -> impl Bundle {
     (
         Name::new("Header"),
         Text(text.into()),
         TextFont::from_font_size(40.0),
+        BaseFontSize(40.0),
+        TextColor(HEADER_TEXT),
+    )
+}
+
+pub fn small_text(text: impl Into<String>) -> impl Bundle {
+    (
+        Name::new("Label"),
+        Text(text.into()),
+        TextFont::from_font_size(20.0),
+        BaseFontSize(20.0),
         TextColor(HEADER_TEXT),
     )
 }